@@ -102,6 +102,7 @@ type (
 		account            *models.Account
 		previousVersion    string
 		moduleMu           sync.Mutex
+		shutdownOnce       sync.Once
 		AnilistDataLoaded  bool // Whether the Anilist data from the first request has been fetched
 	}
 )
@@ -143,6 +144,14 @@ func NewApp(configOpts *ConfigOptions, selfupdater *updater.SelfUpdater) *App {
 		logger.Info().Msg("app: Desktop sidecar mode enabled")
 	}
 
+	// Make sure no other server uses the same database
+	// Seanime Desktop locks the data directory before spawning its sidecar
+	if !configOpts.IsDesktopSidecar {
+		if err := LockDataDir(cfg.Data.AppDataDir, cfg.Server.Host, cfg.Server.Port); err != nil {
+			logger.Fatal().Err(err).Msgf("app: Failed to lock the data directory")
+		}
+	}
+
 	// Initialize the database
 	database, err := db.NewDatabase(cfg.Data.AppDataDir, cfg.Database.Name, logger)
 	if err != nil {
@@ -138,6 +138,11 @@ func NewConfig(options *ConfigOptions, logger *zerolog.Logger) (*Config, error)
 		return nil, err
 	}
 
+	// The desktop app picks the sidecar's port before launching it, so it takes precedence over the config file
+	if options.IsDesktopSidecar && os.Getenv("SEANIME_SERVER_PORT") != "" {
+		cfg.Server.Port = defaultPort
+	}
+
 	// Update the config if the version has changed
 	if err := updateVersion(cfg, options); err != nil {
 		return nil, err
@@ -0,0 +1,74 @@
+package core
+
+import (
+	"encoding/json"
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"time"
+)
+
+// DataDirLockFileName is the file locked by the process using a data directory.
+// Seanime Desktop locks it on behalf of its sidecar, so the sidecar doesn't lock it itself.
+const DataDirLockFileName = "seanime.lock"
+
+var ErrDataDirLocked = errors.New("data directory is used by another Seanime process")
+
+// DataDirLockOwner is written to the lock file so that other processes can tell who uses the data directory.
+type DataDirLockOwner struct {
+	Pid       int    `json:"pid"`
+	Kind      string `json:"kind"` // "server" or "desktop"
+	Host      string `json:"host,omitempty"`
+	Port      int    `json:"port"`
+	Profile   string `json:"profile,omitempty"` // Desktop profile
+	StartedAt int64  `json:"startedAt"`         // Unix milliseconds
+}
+
+// dataDirLock is kept open for as long as the server runs, the lock is released when the process exits
+var dataDirLock *os.File
+
+// LockDataDir takes an advisory lock on the data directory so that two servers never share the same database.
+func LockDataDir(dataDir string, host string, port int) error {
+	path := filepath.Join(dataDir, DataDirLockFileName)
+	f, err := os.OpenFile(path, os.O_RDWR|os.O_CREATE, 0644)
+	if err != nil {
+		return err
+	}
+
+	locked, err := tryLockFile(f)
+	if err != nil {
+		_ = f.Close()
+		return err
+	}
+	if !locked {
+		_ = f.Close()
+		var owner DataDirLockOwner
+		if content, err := os.ReadFile(path); err == nil && json.Unmarshal(content, &owner) == nil {
+			if owner.Kind == "desktop" {
+				return fmt.Errorf("%w: Seanime Desktop (pid %d)", ErrDataDirLocked, owner.Pid)
+			}
+			return fmt.Errorf("%w: server on port %d (pid %d)", ErrDataDirLocked, owner.Port, owner.Pid)
+		}
+		return ErrDataDirLocked
+	}
+
+	owner, _ := json.MarshalIndent(DataDirLockOwner{
+		Pid:       os.Getpid(),
+		Kind:      "server",
+		Host:      host,
+		Port:      port,
+		StartedAt: time.Now().UnixMilli(),
+	}, "", "  ")
+	if err := f.Truncate(0); err != nil {
+		_ = f.Close()
+		return err
+	}
+	if _, err := f.WriteAt(owner, 0); err != nil {
+		_ = f.Close()
+		return err
+	}
+
+	dataDirLock = f
+	return nil
+}
@@ -0,0 +1,42 @@
+package core
+
+import (
+	"crypto/subtle"
+	"os"
+)
+
+// DesktopShutdownTokenEnv holds the token Seanime Desktop sends to shut its sidecar down.
+const DesktopShutdownTokenEnv = "SEANIME_DESKTOP_SHUTDOWN_TOKEN"
+
+// Shutdown stops the modules and closes the database so that the process can exit without losing data.
+// Only the first call does anything.
+func (a *App) Shutdown() {
+	a.shutdownOnce.Do(func() {
+		a.Logger.Info().Msg("app: Shutting down")
+		a.Cleanup()
+		if a.Database != nil {
+			if err := a.Database.Close(); err != nil {
+				a.Logger.Error().Err(err).Msg("app: Failed to close the database")
+			}
+		}
+		a.Logger.Info().Msg("app: Shut down")
+	})
+}
+
+// Exit shuts the app down, flushes the logs and exits the process.
+func (a *App) Exit() {
+	a.Shutdown()
+	if a.OnFlushLogs != nil {
+		a.OnFlushLogs()
+	}
+	os.Exit(0)
+}
+
+// IsDesktopShutdownToken returns true if the app is a desktop sidecar and token is the one its desktop app gave it.
+func (a *App) IsDesktopShutdownToken(token string) bool {
+	expected := os.Getenv(DesktopShutdownTokenEnv)
+	if !a.IsDesktopSidecar || expected == "" || token == "" {
+		return false
+	}
+	return subtle.ConstantTimeCompare([]byte(token), []byte(expected)) == 1
+}
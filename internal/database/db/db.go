@@ -67,6 +67,15 @@ func NewDatabase(appDataDir, dbName string, logger *zerolog.Logger) (*Database,
 	}, nil
 }
 
+// Close closes the connection to the database, waiting for running queries to finish.
+func (db *Database) Close() error {
+	sqlDB, err := db.gormdb.DB()
+	if err != nil {
+		return err
+	}
+	return sqlDB.Close()
+}
+
 // MigrateTables performs auto migration on the database
 func migrateTables(db *gorm.DB) error {
 	err := db.AutoMigrate(
@@ -117,6 +117,7 @@ func InitRoutes(app *core.App, e *echo.Echo) {
 	v1.HEAD("/proxy", util.M3U8Proxy)
 
 	v1.GET("/status", h.HandleGetStatus)
+	v1.POST("/desktop/shutdown", h.HandleDesktopShutdown)
 	v1.GET("/log/*", h.HandleGetLogContent)
 	v1.GET("/logs/filenames", h.HandleGetLogFilenames)
 	v1.DELETE("/logs", h.HandleDeleteLogs)
@@ -1,8 +1,10 @@
 package handlers
 
 import (
+	"errors"
 	"fmt"
 	"io/fs"
+	"net/http"
 	"os"
 	"path/filepath"
 	"runtime"
@@ -291,3 +293,24 @@ func (h *Handler) HandleGetLatestLogContent(c echo.Context) error {
 
 	return h.RespondWithData(c, string(contentB))
 }
+
+// HandleDesktopShutdown
+//
+//	@summary shuts the server down gracefully.
+//	@desc This is used by Seanime Desktop to stop its sidecar on every platform.
+//	@desc The request must carry the token the desktop app gave the sidecar in the X-Seanime-Shutdown-Token header.
+//	@route /api/v1/desktop/shutdown [POST]
+//	@returns bool
+func (h *Handler) HandleDesktopShutdown(c echo.Context) error {
+	if !h.App.IsDesktopShutdownToken(c.Request().Header.Get("X-Seanime-Shutdown-Token")) {
+		return c.JSON(http.StatusForbidden, NewErrorResponse(errors.New("invalid shutdown token")))
+	}
+
+	// Exit once the response is sent
+	go func() {
+		time.Sleep(100 * time.Millisecond)
+		h.App.Exit()
+	}()
+
+	return h.RespondWithData(c, true)
+}
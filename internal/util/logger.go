@@ -81,20 +81,23 @@ func WriteGlobalLogBufferToFile(file *os.File) {
 	logBuffer.Reset()
 }
 
-func SetupLoggerSignalHandling(file *os.File) {
-	if file == nil {
-		return
-	}
-
+// SetupLoggerSignalHandling exits the process on SIGINT and SIGTERM.
+// onExit is called first so the app can shut down, then the log buffer is flushed to the log file.
+func SetupLoggerSignalHandling(file *os.File, onExit func()) {
 	sigChan := make(chan os.Signal, 1)
 	signal.Notify(sigChan, syscall.SIGINT, syscall.SIGTERM)
 
 	go func() {
 		sig := <-sigChan
 		log.Trace().Msgf("Received signal: %s", sig)
+		if onExit != nil {
+			onExit()
+		}
 		// Flush log buffer to the log file when the app exits
-		WriteGlobalLogBufferToFile(file)
-		_ = file.Close()
+		if file != nil {
+			WriteGlobalLogBufferToFile(file)
+			_ = file.Close()
+		}
 		os.Exit(0)
 	}()
 }
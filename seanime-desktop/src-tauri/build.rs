@@ -1,3 +1,25 @@
 fn main() {
-    tauri_build::build()
+    tauri_build::try_build(tauri_build::Attributes::new().app_manifest(
+        tauri_build::AppManifest::new().commands(&[
+            "server_start",
+            "server_stop",
+            "server_restart",
+            "server_status",
+            "get_last_crash",
+            "get_startup_progress",
+            "get_server_url",
+            "get_log_dir",
+            "get_server_logs",
+            "get_desktop_settings",
+            "set_desktop_settings",
+            "get_profiles",
+            "switch_profile",
+            "connect_to_server",
+            "open_log_dir",
+            "open_data_dir",
+            "copy_diagnostics",
+            "quit_app",
+        ]),
+    ))
+    .expect("failed to run tauri-build")
 }
@@ -0,0 +1,275 @@
+use crate::data_dir_lock;
+use crate::log_buffer::{LogBuffer, LogEntry, LogFilter};
+use crate::logging;
+use crate::mode::{self, ServerMode};
+use crate::profiles::{self, ProfilesEvent, SwitchProfileError};
+use crate::report;
+use crate::server;
+use crate::settings::{DesktopSettings, SettingsError, SettingsStore};
+use crate::startup::{StartupProgress, StartupTracker};
+use crate::state::{InvalidTransition, ServerState, ServerStateEvent, SidecarState};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_opener::OpenerExt;
+
+/// Snapshot of the server returned by the server commands.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub state: ServerState,
+    pub pid: Option<u32>,
+    pub base_url: String,
+    /// Whether the app is connected to an existing server instead of its own sidecar
+    pub remote: bool,
+    /// Warnings and errors logged by the current server process
+    pub warning_count: u32,
+    pub error_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommandErrorKind {
+    /// The request doesn't make sense in the current server state
+    InvalidState,
+    /// The requested resource isn't available
+    Unavailable,
+    /// The settings were rejected or couldn't be saved
+    InvalidSettings,
+}
+
+/// Error returned to the webview by the commands.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub kind: CommandErrorKind,
+    pub message: String,
+}
+
+impl From<InvalidTransition> for CommandError {
+    fn from(e: InvalidTransition) -> Self {
+        Self {
+            kind: CommandErrorKind::InvalidState,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<SettingsError> for CommandError {
+    fn from(e: SettingsError) -> Self {
+        Self {
+            kind: CommandErrorKind::InvalidSettings,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Result of the "set_desktop_settings" command.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSettingsResult {
+    pub settings: DesktopSettings,
+    /// Whether the server was restarted to apply the settings
+    pub restarted: bool,
+}
+
+impl From<SwitchProfileError> for CommandError {
+    fn from(e: SwitchProfileError) -> Self {
+        match e {
+            SwitchProfileError::Settings(e) => e.into(),
+            SwitchProfileError::Server(e) => e.into(),
+        }
+    }
+}
+
+fn unavailable(message: impl Into<String>) -> CommandError {
+    CommandError {
+        kind: CommandErrorKind::Unavailable,
+        message: message.into(),
+    }
+}
+
+/// Opens a directory in the file manager.
+fn open_dir(app: &AppHandle, dir: &Path) -> Result<(), CommandError> {
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| unavailable(format!("Failed to open {}: {}", dir.display(), e)))
+}
+
+fn server_status_of(app: &AppHandle) -> ServerStatus {
+    let state = app.state::<SidecarState>();
+    let log_stats = state.log_stats();
+    ServerStatus {
+        state: state.state(),
+        pid: state.server_pid(),
+        base_url: state.base_url(),
+        remote: state.is_remote(),
+        warning_count: log_stats.warnings,
+        error_count: log_stats.errors,
+    }
+}
+
+/// Launches the server if it isn't running.
+#[tauri::command]
+pub async fn server_start(app: AppHandle) -> Result<ServerStatus, CommandError> {
+    server::start_seanime_server(&app).await?;
+    Ok(server_status_of(&app))
+}
+
+/// Stops the server and waits for it to exit.
+#[tauri::command]
+pub async fn server_stop(app: AppHandle) -> Result<ServerStatus, CommandError> {
+    server::stop_seanime_server(&app).await?;
+    Ok(server_status_of(&app))
+}
+
+/// Stops the server if it is running and launches a new one.
+#[tauri::command]
+pub async fn server_restart(app: AppHandle) -> Result<ServerStatus, CommandError> {
+    server::restart_seanime_server(&app).await?;
+    Ok(server_status_of(&app))
+}
+
+#[tauri::command]
+pub async fn server_status(app: AppHandle) -> Result<ServerStatus, CommandError> {
+    Ok(server_status_of(&app))
+}
+
+/// Returns the "server-state" event of the current crash, if the server is crashed.
+/// Lets the crash screen catch up on the crash it was opened for.
+#[tauri::command]
+pub async fn get_last_crash(
+    state: State<'_, SidecarState>,
+) -> Result<Option<ServerStateEvent>, CommandError> {
+    Ok(state
+        .last_crash()
+        .filter(|_| state.state() == ServerState::Crashed))
+}
+
+/// Returns the startup stages reached by the server being launched.
+/// Lets the splashscreen catch up on the stages reached before it was opened.
+#[tauri::command]
+pub async fn get_startup_progress(
+    tracker: State<'_, StartupTracker>,
+) -> Result<StartupProgress, CommandError> {
+    Ok(tracker.progress())
+}
+
+/// Returns the base URL of the running server.
+#[tauri::command]
+pub async fn get_server_url(state: State<'_, SidecarState>) -> Result<String, CommandError> {
+    Ok(state.base_url())
+}
+
+/// Returns the directory containing the desktop and server log files.
+#[tauri::command]
+pub async fn get_log_dir() -> Result<String, CommandError> {
+    logging::log_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .ok_or_else(|| unavailable("Log files are not initialized"))
+}
+
+/// Opens the directory containing the log files.
+#[tauri::command]
+pub async fn open_log_dir(app: AppHandle) -> Result<(), CommandError> {
+    let dir = logging::log_dir().ok_or_else(|| unavailable("Log files are not initialized"))?;
+    open_dir(&app, &dir)
+}
+
+/// Opens the data directory of the active profile.
+#[tauri::command]
+pub async fn open_data_dir(app: AppHandle) -> Result<(), CommandError> {
+    let profile = app.state::<SettingsStore>().get().active().clone();
+    let dir = data_dir_lock::data_dir_of(&app, &profile)
+        .ok_or_else(|| unavailable("The data directory could not be determined"))?;
+    open_dir(&app, &dir)
+}
+
+/// Copies a summary of the app, the server and its last crash to the clipboard and returns it.
+#[tauri::command]
+pub async fn copy_diagnostics(app: AppHandle) -> Result<String, CommandError> {
+    let summary = report::diagnostics_summary(&app);
+    app.clipboard()
+        .write_text(summary.clone())
+        .map_err(|e| unavailable(format!("Failed to copy to the clipboard: {}", e)))?;
+    Ok(summary)
+}
+
+/// Stops the server and closes the app.
+#[tauri::command]
+pub async fn quit_app(app: AppHandle) -> Result<(), CommandError> {
+    app.exit(0);
+    Ok(())
+}
+
+/// Returns the most recent server output lines kept in memory.
+#[tauri::command]
+pub async fn get_server_logs(
+    logs: State<'_, LogBuffer>,
+    filter: Option<LogFilter>,
+) -> Result<Vec<LogEntry>, CommandError> {
+    Ok(logs.query(&filter.unwrap_or_default()))
+}
+
+#[tauri::command]
+pub async fn get_desktop_settings(
+    settings: State<'_, SettingsStore>,
+) -> Result<DesktopSettings, CommandError> {
+    Ok(settings.get())
+}
+
+/// Saves the desktop settings and restarts the server if they affect it.
+#[tauri::command]
+pub async fn set_desktop_settings(
+    app: AppHandle,
+    settings: DesktopSettings,
+) -> Result<SetSettingsResult, CommandError> {
+    let store = app.state::<SettingsStore>();
+    let previous = store.set(settings)?;
+    let settings = store.get();
+
+    let state = app.state::<SidecarState>();
+    // The launch flag takes precedence over the saved server mode
+    state.set_mode(ServerMode::from_launch_args().unwrap_or_else(|| settings.server_mode.clone()));
+
+    if previous.profiles != settings.profiles || previous.active_profile != settings.active_profile
+    {
+        profiles::emit_profiles(&app);
+    }
+
+    let restarted = previous.requires_restart(&settings) && state.state() != ServerState::Stopped;
+    if restarted {
+        server::restart_seanime_server(&app).await?;
+    }
+    Ok(SetSettingsResult {
+        settings,
+        restarted,
+    })
+}
+
+#[tauri::command]
+pub async fn get_profiles(app: AppHandle) -> Result<ProfilesEvent, CommandError> {
+    Ok(profiles::profiles_of(&app))
+}
+
+/// Relaunches the server with another profile and reloads the main window.
+#[tauri::command]
+pub async fn switch_profile(app: AppHandle, name: String) -> Result<ProfilesEvent, CommandError> {
+    profiles::switch_profile(&app, &name).await?;
+    Ok(profiles::profiles_of(&app))
+}
+
+/// Connects to an existing server for the rest of the session instead of launching the sidecar,
+/// e.g. the one using the data directory of the active profile.
+#[tauri::command]
+pub async fn connect_to_server(app: AppHandle, url: String) -> Result<ServerStatus, CommandError> {
+    let url = mode::parse_server_url(&url).map_err(|e| CommandError {
+        kind: CommandErrorKind::InvalidSettings,
+        message: format!("invalid server URL {:?}: {}", url, e),
+    })?;
+    app.state::<SidecarState>()
+        .set_mode(ServerMode::Remote(url));
+    server::restart_seanime_server(&app).await?;
+    Ok(server_status_of(&app))
+}
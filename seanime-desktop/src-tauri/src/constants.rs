@@ -1,3 +1,56 @@
 pub const MAIN_WINDOW_LABEL: &str = "main";
 pub const SPLASHSCREEN_WINDOW_LABEL: &str = "splashscreen";
 pub const CRASH_SCREEN_WINDOW_LABEL: &str = "crash_screen";
+
+pub const SERVER_HOST: &str = "127.0.0.1";
+pub const DEFAULT_SERVER_PORT: u16 = 43211;
+pub const SERVER_STATUS_PATH: &str = "/api/v1/status";
+pub const SERVER_SHUTDOWN_PATH: &str = "/api/v1/desktop/shutdown";
+
+// Readiness probe defaults
+pub const READINESS_TIMEOUT_SECS: u64 = 60;
+pub const READINESS_INITIAL_BACKOFF_MS: u64 = 100;
+pub const READINESS_MAX_BACKOFF_MS: u64 = 2000;
+pub const READINESS_REQUEST_TIMEOUT_MS: u64 = 1000;
+
+// Health watchdog defaults
+pub const HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
+pub const HEALTH_CHECK_TIMEOUT_MS: u64 = 5000;
+pub const HEALTH_CHECK_FAILURE_THRESHOLD: u32 = 3;
+
+// Sidecar restart policy defaults
+pub const RESTART_MAX_RETRIES: u32 = 5;
+pub const RESTART_INITIAL_BACKOFF_MS: u64 = 500;
+pub const RESTART_MAX_BACKOFF_MS: u64 = 30_000;
+pub const RESTART_RESET_WINDOW_SECS: u64 = 300;
+
+// Time given to the sidecar to exit on its own before it is killed
+pub const SHUTDOWN_DRAIN_TIMEOUT_SECS: u64 = 5;
+pub const SHUTDOWN_REQUEST_TIMEOUT_MS: u64 = 1000;
+
+// Number of stderr lines kept for the crash screen
+pub const STDERR_TAIL_LINES: usize = 30;
+
+// Number of recent output lines shown when the server doesn't start in time
+pub const STARTUP_TIMEOUT_LOG_LINES: usize = 50;
+
+// Number of recent output lines searched for the cause of a crash
+pub const CRASH_DIAGNOSIS_LOG_LINES: usize = 100;
+
+// Number of recent output lines included in the diagnostics copied from the crash screen
+pub const DIAGNOSTICS_LOG_LINES: usize = 100;
+
+// Time the panic hook waits for the crash report to be written
+pub const CRASH_REPORT_TIMEOUT_MS: u64 = 3000;
+
+// Time the panic hook waits for the crash screen before exiting
+pub const CRASH_SCREEN_TIMEOUT_SECS: u64 = 5;
+
+// Log file retention defaults
+pub const LOG_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+pub const LOG_MAX_FILES: usize = 5;
+pub const LOG_MAX_AGE_DAYS: u64 = 14;
+pub const LOG_ROTATION_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+// Number of server output lines kept in memory
+pub const LOG_BUFFER_CAPACITY: usize = 5000;
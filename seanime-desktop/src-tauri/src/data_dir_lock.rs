@@ -0,0 +1,209 @@
+use crate::constants::SERVER_HOST;
+use crate::health;
+use crate::settings::Profile;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// File locked by the process using a data directory, also locked by the standalone server.
+/// It holds a [LockOwner] so other processes can tell who uses the directory.
+pub const DATA_DIR_LOCK_FILE_NAME: &str = "seanime.lock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockOwnerKind {
+    /// Seanime Desktop, on behalf of its sidecar
+    Desktop,
+    /// A standalone server
+    Server,
+}
+
+/// Process holding the lock of a data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockOwner {
+    pub pid: u32,
+    pub kind: LockOwnerKind,
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Port of the server using the data directory
+    pub port: u16,
+    /// Profile of the desktop app holding the lock
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Milliseconds since the Unix epoch at which the lock was taken
+    pub started_at: i64,
+}
+
+impl LockOwner {
+    pub fn desktop(port: u16, profile: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            kind: LockOwnerKind::Desktop,
+            host: Some(SERVER_HOST.to_string()),
+            port,
+            profile: Some(profile.to_string()),
+            started_at: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+
+    /// URL at which the server using the data directory can be reached from this machine.
+    pub fn server_url(&self) -> String {
+        match self.host.as_deref() {
+            Some(host) if !matches!(host, "" | "0.0.0.0" | "::" | "[::]") => {
+                format!("http://{}:{}", host, self.port)
+            }
+            _ => health::server_base_url(self.port),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.kind, &self.profile) {
+            (LockOwnerKind::Desktop, Some(profile)) => format!(
+                "another Seanime Desktop window (process {}, profile {:?})",
+                self.pid, profile
+            ),
+            (LockOwnerKind::Desktop, None) => {
+                format!("another Seanime Desktop window (process {})", self.pid)
+            }
+            (LockOwnerKind::Server, _) => format!(
+                "a Seanime server (process {}) listening on port {}",
+                self.pid, self.port
+            ),
+        }
+    }
+}
+
+/// Sent to the crash screen when another process uses the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataDirConflict {
+    pub data_dir: String,
+    pub owner: Option<LockOwner>,
+    /// URL of the server using the data directory, to connect to it instead
+    pub server_url: Option<String>,
+    /// Other profiles the sidecar can be launched with
+    pub profiles: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process holds the lock, its owner is None if it couldn't be read
+    Held(Option<LockOwner>),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::Held(Some(owner)) => {
+                write!(f, "the data directory is used by {}", owner.describe())
+            }
+            LockError::Held(None) => write!(f, "the data directory is used by another process"),
+            LockError::Io(e) => write!(f, "could not lock the data directory: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LockError {}
+
+impl From<io::Error> for LockError {
+    fn from(e: io::Error) -> Self {
+        LockError::Io(e)
+    }
+}
+
+/// Advisory lock on a data directory, released when dropped or when the desktop process exits.
+pub struct DataDirLock {
+    _file: File,
+}
+
+impl DataDirLock {
+    /// Locks `data_dir`, creating it if needed, and records `owner` in the lock file.
+    pub fn acquire(data_dir: &Path, owner: &LockOwner) -> Result<Self, LockError> {
+        fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(DATA_DIR_LOCK_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        if !try_lock(&file)? {
+            return Err(LockError::Held(read_owner(&path)));
+        }
+        file.set_len(0)?;
+        file.write_all(&serde_json::to_vec_pretty(owner).map_err(io::Error::from)?)?;
+        file.sync_all()?;
+        Ok(Self { _file: file })
+    }
+}
+
+fn read_owner(path: &Path) -> Option<LockOwner> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Data directory the sidecar uses with `profile`, mirroring how the server resolves it.
+pub fn data_dir_of(app: &AppHandle, profile: &Profile) -> Option<PathBuf> {
+    if let Some(data_dir) = &profile.data_dir {
+        return Some(PathBuf::from(data_dir));
+    }
+    // Test data dir used during development
+    if cfg!(dev) {
+        if let Some(data_dir) = option_env!("TEST_DATADIR") {
+            return Some(PathBuf::from(data_dir));
+        }
+    }
+    // The server defaults to the "Seanime" directory in the user config dir
+    app.path().config_dir().ok().map(|dir| dir.join("Seanime"))
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
+
+#[cfg(windows)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::{ERROR_LOCK_VIOLATION, HANDLE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY,
+    };
+    use windows_sys::Win32::System::IO::OVERLAPPED;
+
+    // Locked bytes can't be read on Windows, lock a byte far past the owner record so it stays readable
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    overlapped.Anonymous.Anonymous.Offset = u32::MAX;
+    let locked = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as HANDLE,
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            1,
+            0,
+            &mut overlapped,
+        )
+    };
+    if locked != 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    if e.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
@@ -0,0 +1,455 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+
+/// Known reasons for the server to stop or fail to start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CrashCategory {
+    PortInUse,
+    /// Another server uses the same data directory
+    DataDirInUse,
+    DatabaseLocked,
+    DatabaseCorrupt,
+    MigrationFailed,
+    PermissionDenied,
+    DiskFull,
+    MissingBinary,
+    OutOfMemory,
+    /// Killed by a signal the server didn't handle
+    Killed,
+    /// Go runtime panic or fatal error
+    Panic,
+    StartupTimeout,
+    /// The server kept failing health checks after being restarted
+    Unresponsive,
+    /// The remote server can't be reached
+    Unreachable,
+    /// The desktop app itself panicked, the server may be fine
+    DesktopPanic,
+    Unknown,
+}
+
+/// Explanation of a crash shown on the crash screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashDiagnosis {
+    pub category: CrashCategory,
+    pub title: String,
+    pub explanation: String,
+    /// Things the user can try, most likely to help first
+    pub actions: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// Line of server output the diagnosis is based on
+    pub evidence: Option<String>,
+}
+
+/// Substrings identifying each category in the server output, checked in order and in lowercase.
+const PATTERNS: &[(CrashCategory, &[&str])] = &[
+    (
+        CrashCategory::DataDirInUse,
+        &["data directory is used by another seanime process"],
+    ),
+    (
+        CrashCategory::PortInUse,
+        &[
+            "address already in use",
+            "only one usage of each socket address",
+        ],
+    ),
+    (
+        CrashCategory::DatabaseLocked,
+        &[
+            "database is locked",
+            "sqlite_busy",
+            "database table is locked",
+        ],
+    ),
+    (
+        CrashCategory::DatabaseCorrupt,
+        &[
+            "database disk image is malformed",
+            "file is not a database",
+            "sqlite_corrupt",
+            "sqlite_notadb",
+        ],
+    ),
+    (
+        CrashCategory::DiskFull,
+        &[
+            "no space left on device",
+            "not enough space on the disk",
+            "sqlite_full",
+        ],
+    ),
+    (
+        CrashCategory::PermissionDenied,
+        &[
+            "permission denied",
+            "access is denied",
+            "operation not permitted",
+            "read-only file system",
+            "sqlite_readonly",
+        ],
+    ),
+    (
+        CrashCategory::MigrationFailed,
+        &["failed to perform auto migration"],
+    ),
+    (
+        CrashCategory::OutOfMemory,
+        &["out of memory", "cannot allocate memory"],
+    ),
+    (CrashCategory::Panic, &["panic:", "fatal error:"]),
+];
+
+#[cfg(unix)]
+const SIGKILL: i32 = 9;
+
+impl CrashDiagnosis {
+    pub fn new(category: CrashCategory) -> Self {
+        let (title, explanation, actions): (&str, &str, &[&str]) = match category {
+            CrashCategory::PortInUse => (
+                "Port already in use",
+                "Another program is already listening on the port the server needs.",
+                &[
+                    "Close the other program, or the other Seanime server, and retry.",
+                    "Set SEANIME_DESKTOP_PORT_STRATEGY to \"fallback\" or \"random\" to use another port.",
+                ],
+            ),
+            CrashCategory::DataDirInUse => (
+                "Data directory in use",
+                "Another Seanime server is using the same data directory. Two servers can't share a database.",
+                &[
+                    "Connect to the other server instead.",
+                    "Switch to another profile.",
+                    "Stop the other server and retry.",
+                ],
+            ),
+            CrashCategory::DatabaseLocked => (
+                "Database locked",
+                "The database is locked by another process.",
+                &[
+                    "Close any other Seanime server or program using seanime.db, then retry.",
+                    "Restart your computer if the problem persists.",
+                ],
+            ),
+            CrashCategory::DatabaseCorrupt => (
+                "Database corrupted",
+                "The database file is damaged and can't be read.",
+                &[
+                    "Restore seanime.db from a backup in the data directory.",
+                    "Move seanime.db out of the data directory to start with a new database.",
+                ],
+            ),
+            CrashCategory::MigrationFailed => (
+                "Database update failed",
+                "The server couldn't update the database to its current version.",
+                &[
+                    "Retry, the migration may have been interrupted.",
+                    "Back up seanime.db and report the issue with the logs attached.",
+                ],
+            ),
+            CrashCategory::PermissionDenied => (
+                "Permission denied",
+                "The server isn't allowed to read or write a file it needs, usually in the data directory.",
+                &[
+                    "Make sure your user owns the data directory and can write to it.",
+                    "Check that the data directory isn't on a read-only drive.",
+                ],
+            ),
+            CrashCategory::DiskFull => (
+                "Disk full",
+                "There is no space left on the drive holding the data directory.",
+                &["Free up some space and retry."],
+            ),
+            CrashCategory::MissingBinary => (
+                "Server not found",
+                "The server executable bundled with the app is missing or can't be run.",
+                &[
+                    "Reinstall Seanime.",
+                    "Check that your antivirus didn't quarantine the server executable.",
+                ],
+            ),
+            CrashCategory::OutOfMemory => (
+                "Out of memory",
+                "The server ran out of memory.",
+                &["Close other applications and retry."],
+            ),
+            CrashCategory::Killed => (
+                "Server killed",
+                "The server was killed by the system or another program, possibly because memory was running low.",
+                &["Retry.", "Close other applications if it happens again."],
+            ),
+            CrashCategory::Panic => (
+                "Server error",
+                "The server ran into an unexpected error.",
+                &["Retry.", "Report the issue with the logs attached if it happens again."],
+            ),
+            CrashCategory::StartupTimeout => (
+                "Server not responding",
+                "The server started but didn't respond in time.",
+                &[
+                    "Retry, the first start after an update can take longer.",
+                    "Set SEANIME_DESKTOP_READINESS_TIMEOUT to wait longer.",
+                ],
+            ),
+            CrashCategory::Unresponsive => (
+                "Server not responding",
+                "The server stopped responding and kept doing so after being restarted.",
+                &[
+                    "Retry.",
+                    "Open the logs to see what the server was doing, and report the issue if it happens again.",
+                ],
+            ),
+            CrashCategory::Unreachable => (
+                "Server unreachable",
+                "The Seanime server the app connects to didn't respond.",
+                &[
+                    "Make sure the server is running and reachable from this computer.",
+                    "Check the server URL in the desktop settings.",
+                ],
+            ),
+            CrashCategory::DesktopPanic => (
+                "Seanime Desktop crashed",
+                "The desktop app ran into an unexpected error and has to close. A crash report was saved in the log directory.",
+                &[
+                    "Quit and open Seanime again.",
+                    "Report the issue with the crash report and the logs attached.",
+                ],
+            ),
+            CrashCategory::Unknown => (
+                "Server stopped",
+                "The server stopped unexpectedly.",
+                &["Retry.", "Open the logs to see what happened."],
+            ),
+        };
+        Self {
+            category,
+            title: title.to_string(),
+            explanation: explanation.to_string(),
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            exit_code: None,
+            signal: None,
+            evidence: None,
+        }
+    }
+
+    fn with_evidence(mut self, evidence: Option<String>) -> Self {
+        self.evidence = evidence;
+        self
+    }
+}
+
+/// Finds the first known failure in `lines`, most relevant first.
+fn match_lines<'a, I>(lines: I) -> Option<(CrashCategory, String)>
+where
+    I: IntoIterator<Item = &'a str> + Clone,
+{
+    PATTERNS.iter().find_map(|(category, patterns)| {
+        lines.clone().into_iter().find_map(|line| {
+            let lower = line.to_lowercase();
+            patterns
+                .iter()
+                .any(|pattern| lower.contains(pattern))
+                .then(|| (*category, line.trim().to_string()))
+        })
+    })
+}
+
+/// Diagnoses a server that exited with `exit_code` or `signal`.
+/// `fatal` is the last fatal error it logged and `lines` its last output lines, oldest first.
+pub fn classify(
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    fatal: Option<&str>,
+    lines: &[String],
+) -> CrashDiagnosis {
+    let candidates: Vec<&str> = fatal
+        .into_iter()
+        .chain(lines.iter().rev().map(String::as_str))
+        .collect();
+
+    let mut diagnosis = match match_lines(candidates.iter().copied()) {
+        Some((category, evidence)) => CrashDiagnosis::new(category).with_evidence(Some(evidence)),
+        None => {
+            #[cfg(unix)]
+            let category = match (exit_code, signal) {
+                (_, Some(SIGKILL)) => CrashCategory::Killed,
+                // Exit code of a shell that couldn't find or run the command
+                (Some(126 | 127), _) => CrashCategory::MissingBinary,
+                _ => CrashCategory::Unknown,
+            };
+            #[cfg(not(unix))]
+            let category = CrashCategory::Unknown;
+            CrashDiagnosis::new(category).with_evidence(fatal.map(str::to_string))
+        }
+    };
+    diagnosis.exit_code = exit_code;
+    diagnosis.signal = signal;
+    diagnosis
+}
+
+/// Describes how the server exited, e.g. "exit code 3" or "signal 9".
+/// A status without either is reported as exit code 1.
+pub fn describe_exit(exit_code: Option<i32>, signal: Option<i32>) -> String {
+    match (exit_code, signal) {
+        (Some(code), Some(signal)) => format!("exit code {} (signal {})", code, signal),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (code, None) => format!("exit code {}", code.unwrap_or(1)),
+    }
+}
+
+/// Diagnoses a server that didn't become ready in time, from its last output lines.
+pub fn classify_timeout(lines: &[String], remote: bool) -> CrashDiagnosis {
+    if remote {
+        return CrashDiagnosis::new(CrashCategory::Unreachable);
+    }
+    match match_lines(lines.iter().rev().map(String::as_str)) {
+        Some((category, evidence)) => CrashDiagnosis::new(category).with_evidence(Some(evidence)),
+        None => CrashDiagnosis::new(CrashCategory::StartupTimeout),
+    }
+}
+
+/// Diagnoses a server that couldn't be spawned.
+pub fn classify_spawn_error(error: &io::Error) -> CrashDiagnosis {
+    let category = match error.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied => CrashCategory::MissingBinary,
+        _ => match_lines([error.to_string().as_str()])
+            .map(|(category, _)| category)
+            .unwrap_or(CrashCategory::Unknown),
+    };
+    CrashDiagnosis::new(category).with_evidence(Some(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn classifies_logged_failures() {
+        let cases = [
+            (
+                "2024-05-06 07:08:09 FTL - app > Failed to start error=\"listen tcp 127.0.0.1:43211: bind: address already in use\"",
+                CrashCategory::PortInUse,
+            ),
+            (
+                "listen tcp 127.0.0.1:43211: bind: Only one usage of each socket address is normally permitted.",
+                CrashCategory::PortInUse,
+            ),
+            (
+                "2024-05-06 07:08:09 ERR - db > Failed to query error=\"database is locked\"",
+                CrashCategory::DatabaseLocked,
+            ),
+            ("SQLITE_BUSY: database table is locked", CrashCategory::DatabaseLocked),
+            ("open /data/seanime.db: permission denied", CrashCategory::PermissionDenied),
+            ("open C:\\Seanime\\seanime.db: Access is denied.", CrashCategory::PermissionDenied),
+            ("panic: runtime error: index out of range [3] with length 3", CrashCategory::Panic),
+        ];
+        for (line, category) in cases {
+            let output = lines(&["2024-05-06 07:08:08 INF - app > Seanime v2.0.0", line]);
+            let diagnosis = classify(Some(1), None, None, &output);
+            assert_eq!(diagnosis.category, category, "category of {:?}", line);
+            assert_eq!(
+                diagnosis.evidence.as_deref(),
+                Some(line),
+                "evidence of {:?}",
+                line
+            );
+            assert_eq!(diagnosis.exit_code, Some(1));
+        }
+    }
+
+    #[test]
+    fn prefers_the_fatal_error() {
+        let output = lines(&["open /data/seanime.db: permission denied"]);
+        let fatal = "listen tcp 127.0.0.1:43211: bind: address already in use";
+        let diagnosis = classify(Some(1), None, Some(fatal), &output);
+        assert_eq!(diagnosis.category, CrashCategory::PortInUse);
+        assert_eq!(diagnosis.evidence.as_deref(), Some(fatal));
+    }
+
+    #[test]
+    fn classifies_missing_binary() {
+        #[cfg(unix)]
+        assert_eq!(
+            classify(Some(127), None, None, &[]).category,
+            CrashCategory::MissingBinary
+        );
+
+        let cases = [
+            (io::ErrorKind::NotFound, CrashCategory::MissingBinary),
+            (
+                io::ErrorKind::PermissionDenied,
+                CrashCategory::MissingBinary,
+            ),
+            (io::ErrorKind::Other, CrashCategory::Unknown),
+        ];
+        for (kind, category) in cases {
+            let error = io::Error::new(kind, "could not run the server");
+            assert_eq!(
+                classify_spawn_error(&error).category,
+                category,
+                "category of {:?}",
+                kind
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_signal_exits() {
+        let diagnosis = classify(
+            None,
+            Some(SIGKILL),
+            None,
+            &lines(&["INF - app > Seanime started"]),
+        );
+        assert_eq!(diagnosis.category, CrashCategory::Killed);
+        assert_eq!(diagnosis.signal, Some(SIGKILL));
+
+        // A signal the server handles isn't a crash cause on its own
+        assert_eq!(
+            classify(None, Some(15), None, &[]).category,
+            CrashCategory::Unknown
+        );
+    }
+
+    #[test]
+    fn classifies_unknown_exits() {
+        let output = lines(&["2024-05-06 07:08:09 INF - app > Seanime started at 127.0.0.1:43211"]);
+        let diagnosis = classify(Some(1), None, None, &output);
+        assert_eq!(diagnosis.category, CrashCategory::Unknown);
+        assert_eq!(diagnosis.evidence, None);
+        assert_eq!(diagnosis.exit_code, Some(1));
+    }
+
+    #[test]
+    fn classifies_timeouts() {
+        assert_eq!(
+            classify_timeout(&[], true).category,
+            CrashCategory::Unreachable
+        );
+        assert_eq!(
+            classify_timeout(&[], false).category,
+            CrashCategory::StartupTimeout
+        );
+        let output = lines(&["ERR - db > Failed to open error=\"database is locked\""]);
+        assert_eq!(
+            classify_timeout(&output, false).category,
+            CrashCategory::DatabaseLocked
+        );
+    }
+
+    #[test]
+    fn describes_exits() {
+        assert_eq!(describe_exit(Some(3), None), "exit code 3");
+        assert_eq!(describe_exit(None, Some(9)), "signal 9");
+        assert_eq!(describe_exit(Some(0), Some(15)), "exit code 0 (signal 15)");
+        assert_eq!(describe_exit(None, None), "exit code 1");
+    }
+}
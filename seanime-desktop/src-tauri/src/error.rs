@@ -0,0 +1,75 @@
+use crate::state::InvalidTransition;
+use std::io;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Errors of the desktop app itself, as opposed to the server's.
+#[derive(Debug)]
+pub enum DesktopError {
+    /// A window the app relies on doesn't exist, identified by its label
+    WindowNotFound(&'static str),
+    /// The app was bundled without a window icon
+    MissingIcon,
+    Tauri(tauri::Error),
+    Shell(tauri_plugin_shell::Error),
+    Json(serde_json::Error),
+    Io(io::Error),
+    InvalidTransition(InvalidTransition),
+}
+
+impl std::fmt::Display for DesktopError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DesktopError::WindowNotFound(label) => write!(f, "window {:?} not found", label),
+            DesktopError::MissingIcon => write!(f, "the app has no window icon"),
+            DesktopError::Tauri(e) => write!(f, "{}", e),
+            DesktopError::Shell(e) => write!(f, "{}", e),
+            DesktopError::Json(e) => write!(f, "{}", e),
+            DesktopError::Io(e) => write!(f, "{}", e),
+            DesktopError::InvalidTransition(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for DesktopError {}
+
+impl From<tauri::Error> for DesktopError {
+    fn from(e: tauri::Error) -> Self {
+        DesktopError::Tauri(e)
+    }
+}
+
+impl From<tauri_plugin_shell::Error> for DesktopError {
+    fn from(e: tauri_plugin_shell::Error) -> Self {
+        DesktopError::Shell(e)
+    }
+}
+
+impl From<serde_json::Error> for DesktopError {
+    fn from(e: serde_json::Error) -> Self {
+        DesktopError::Json(e)
+    }
+}
+
+impl From<io::Error> for DesktopError {
+    fn from(e: io::Error) -> Self {
+        DesktopError::Io(e)
+    }
+}
+
+impl From<InvalidTransition> for DesktopError {
+    fn from(e: InvalidTransition) -> Self {
+        DesktopError::InvalidTransition(e)
+    }
+}
+
+/// Locking that survives a thread panicking while it held the lock.
+/// The panic is reported by the panic hook, the data stays usable for the rest of the app.
+pub trait LockExt<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
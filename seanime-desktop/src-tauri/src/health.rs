@@ -0,0 +1,199 @@
+use crate::constants::{
+    READINESS_INITIAL_BACKOFF_MS, READINESS_MAX_BACKOFF_MS, READINESS_REQUEST_TIMEOUT_MS,
+    READINESS_TIMEOUT_SECS, SERVER_HOST, SERVER_STATUS_PATH,
+};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+/// Returns the base URL of the sidecar listening on `port`.
+pub fn server_base_url(port: u16) -> String {
+    format!("http://{}:{}", SERVER_HOST, port)
+}
+
+/// Timeout and backoff used while waiting for the server to become ready.
+#[derive(Debug, Clone)]
+pub struct ReadinessConfig {
+    /// Startup deadline, the server is killed if it isn't ready once it expires
+    pub timeout: Duration,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for ReadinessConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(READINESS_TIMEOUT_SECS),
+            initial_backoff: Duration::from_millis(READINESS_INITIAL_BACKOFF_MS),
+            max_backoff: Duration::from_millis(READINESS_MAX_BACKOFF_MS),
+            request_timeout: Duration::from_millis(READINESS_REQUEST_TIMEOUT_MS),
+        }
+    }
+}
+
+impl ReadinessConfig {
+    /// Default config, with the timeout overridable through `SEANIME_DESKTOP_READINESS_TIMEOUT` (seconds).
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(secs) = std::env::var("SEANIME_DESKTOP_READINESS_TIMEOUT")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+        {
+            config.timeout = Duration::from_secs(secs);
+        }
+        config
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadinessError {
+    /// The server did not answer successfully before the deadline
+    TimedOut(Duration),
+    /// The caller gave up waiting (e.g. the process exited)
+    Aborted,
+    /// The HTTP client used to probe the server couldn't be created
+    Client(reqwest::Error),
+}
+
+impl std::fmt::Display for ReadinessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadinessError::TimedOut(d) => {
+                write!(
+                    f,
+                    "server did not become ready within {} seconds",
+                    d.as_secs()
+                )
+            }
+            ReadinessError::Aborted => write!(f, "readiness probe aborted"),
+            ReadinessError::Client(e) => {
+                write!(f, "could not create the health check client: {}", e)
+            }
+        }
+    }
+}
+
+/// Returns true if the status endpoint answers with a success status code.
+pub async fn check_status(client: &reqwest::Client, base_url: &str) -> bool {
+    let url = format!("{}{}", base_url.trim_end_matches('/'), SERVER_STATUS_PATH);
+    match client.get(url).send().await {
+        Ok(res) => res.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Polls the status endpoint with exponential backoff until it succeeds.
+/// `should_abort` is checked before every attempt so the caller can stop waiting early.
+/// Returns the time it took for the server to become ready.
+pub async fn wait_until_ready<F>(
+    base_url: &str,
+    config: &ReadinessConfig,
+    should_abort: F,
+) -> Result<Duration, ReadinessError>
+where
+    F: Fn() -> bool,
+{
+    let client = reqwest::Client::builder()
+        .timeout(config.request_timeout)
+        .build()
+        .map_err(ReadinessError::Client)?;
+
+    let start = Instant::now();
+    let mut backoff = config.initial_backoff;
+
+    loop {
+        if should_abort() {
+            return Err(ReadinessError::Aborted);
+        }
+        if check_status(&client, base_url).await {
+            return Ok(start.elapsed());
+        }
+        if start.elapsed() >= config.timeout {
+            return Err(ReadinessError::TimedOut(config.timeout));
+        }
+        sleep(backoff).await;
+        backoff = std::cmp::min(backoff * 2, config.max_backoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn config(timeout_ms: u64) -> ReadinessConfig {
+        ReadinessConfig {
+            timeout: Duration::from_millis(timeout_ms),
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            request_timeout: Duration::from_millis(200),
+        }
+    }
+
+    /// Serves every request on a local port with `status`, returning the server's base URL.
+    fn serve(status: &'static str) -> String {
+        let listener = TcpListener::bind((SERVER_HOST, 0)).unwrap();
+        let base_url = server_base_url(listener.local_addr().unwrap().port());
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        base_url
+    }
+
+    /// Base URL of a local port nothing listens on.
+    fn closed_port() -> String {
+        let listener = TcpListener::bind((SERVER_HOST, 0)).unwrap();
+        server_base_url(listener.local_addr().unwrap().port())
+    }
+
+    #[test]
+    fn ready_once_the_status_endpoint_answers() {
+        let base_url = serve("200 OK");
+        let result = block_on(wait_until_ready(&base_url, &config(2000), || false));
+        assert!(
+            result.is_ok(),
+            "expected the server to be ready, got {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn times_out_when_the_server_never_answers() {
+        for base_url in [serve("503 Service Unavailable"), closed_port()] {
+            match block_on(wait_until_ready(&base_url, &config(200), || false)) {
+                Err(ReadinessError::TimedOut(timeout)) => {
+                    assert_eq!(timeout, Duration::from_millis(200))
+                }
+                other => panic!("expected a timeout for {}, got {:?}", base_url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn stops_when_aborted() {
+        let base_url = closed_port();
+        let result = block_on(wait_until_ready(&base_url, &config(10_000), || true));
+        assert!(
+            matches!(result, Err(ReadinessError::Aborted)),
+            "got {:?}",
+            result
+        );
+    }
+}
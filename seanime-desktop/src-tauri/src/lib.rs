@@ -1,165 +1,130 @@
+mod commands;
 mod constants;
+mod data_dir_lock;
+mod diagnosis;
+mod error;
+mod health;
+mod log_buffer;
+mod logging;
+mod mode;
+mod orphan;
+mod output;
+mod panic_hook;
+mod platform;
+mod port;
+#[cfg(target_os = "linux")]
+pub mod process_group;
+mod profiles;
+mod report;
+mod screens;
 mod server;
+mod settings;
+mod shutdown;
+mod sidecar;
+mod startup;
+mod state;
+mod supervisor;
 #[cfg(desktop)]
 mod tray;
+mod watchdog;
+mod zerolog;
 
-use constants::MAIN_WINDOW_LABEL;
-use std::sync::{Arc, Mutex};
+use constants::{CRASH_SCREEN_WINDOW_LABEL, LOG_BUFFER_CAPACITY, MAIN_WINDOW_LABEL};
+use error::DesktopError;
+use log_buffer::LogBuffer;
+use logging::{log_error, log_info};
+use mode::ServerMode;
+use orphan::{RuntimeFile, RUNTIME_FILE_NAME};
+use settings::{SettingsStore, SETTINGS_FILE_NAME};
+use startup::StartupTracker;
+use state::{ServerState, SidecarState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use supervisor::Supervisor;
 #[cfg(target_os = "macos")]
 use tauri::utils::TitleBarStyle;
-use tauri::{Emitter, Listener, Manager};
-use tauri_plugin_os;
+#[cfg(target_os = "macos")]
+use tauri::Emitter;
+use tauri::{Listener, Manager};
 
-pub fn run() {
-    let server_process = Arc::new(Mutex::new(
-        None::<tauri_plugin_shell::process::CommandChild>,
-    ));
-    let server_process_for_setup = Arc::clone(&server_process);
-    let server_process_for_restart = Arc::clone(&server_process);
-    //
-    let is_shutdown = Arc::new(Mutex::new(false));
-    let is_shutdown_for_setup = Arc::clone(&is_shutdown);
-    let is_shutdown_for_restart = Arc::clone(&is_shutdown);
+/// Set once an exit request is stopping the server, later requests wait for it
+static STOPPING_FOR_EXIT: AtomicBool = AtomicBool::new(false);
+/// Set once the server is stopped, the app can then exit
+static EXIT_READY: AtomicBool = AtomicBool::new(false);
 
-    let server_started = Arc::new(Mutex::new(false));
-    let server_started_for_setup = Arc::clone(&server_started);
-    let server_started_for_restart = Arc::clone(&server_started);
+pub fn run() {
+    platform::init(&platform::PlatformConfig::from_env());
 
     tauri::Builder::default()
         .plugin(tauri_plugin_single_instance::init(|app, _cmd, _args| {
-            if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
-                window.show().unwrap();
-                window.set_focus().unwrap();
+            if let Err(e) = screens::show_main_window(app) {
+                log_error!("Failed to show the main window: {}", e);
             }
         }))
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_clipboard_manager::init())
-        .setup(move |app| {
-            #[cfg(all(desktop))]
-            {
-                let handle = app.handle();
-                tray::create_tray(handle)?;
-            }
-
-            let main_window = app.get_webview_window(MAIN_WINDOW_LABEL).unwrap();
-            main_window.hide().unwrap();
-
-            // Set overlay title bar only when building for macOS
-            #[cfg(target_os = "macos")]
-            main_window
-                .set_title_bar_style(TitleBarStyle::Overlay)
-                .unwrap();
-
-            // Hide the title bar on Windows
-            #[cfg(any(target_os = "windows"))]
-            main_window.set_decorations(false).unwrap();
-
-            // Open dev tools only when in dev mode
-            #[cfg(debug_assertions)]
-            {
-                main_window.open_devtools();
-            }
-
-            server::launch_seanime_server(
-                app.handle().clone(),
-                server_process_for_setup,
-                is_shutdown_for_setup,
-                server_started_for_setup,
-            );
-
-            let app_handle = app.handle().clone();
-            app.listen("restart-server", move |_| {
-                println!("EVENT restart-server");
-                let mut child_guard = server_process_for_restart.lock().unwrap();
-                if let Some(child) = child_guard.take() {
-                    println!("Killing existing server process");
-                    // Kill the existing server process
-                    if let Err(e) = child.kill() {
-                        eprintln!("Failed to kill server process: {}", e);
-                    }
-                }
-                server::launch_seanime_server(
-                    app_handle.clone(),
-                    Arc::clone(&server_process_for_restart),
-                    Arc::clone(&is_shutdown_for_restart),
-                    Arc::clone(&server_started_for_restart),
-                );
-            });
-
-            let app_handle_1 = app.handle().clone();
-            let main_window_clone = main_window.clone();
-            main_window.listen("macos-activation-policy-accessory", move |_| {
-                println!("EVENT macos-activation-policy-accessory");
-                #[cfg(target_os = "macos")]
-                {
-                    if let Err(e) = app_handle_1.set_activation_policy(tauri::ActivationPolicy::Accessory) {
-                        eprintln!("Failed to set activation policy to accessory: {}", e);
-                    } else {
-                        if let Err(e) = main_window_clone.show() {
-                            eprintln!("Failed to show main window: {}", e);
-                        }
-                        if let Err(e) = main_window_clone.set_fullscreen(true) {
-                            eprintln!("Failed to set fullscreen: {}", e);
-                        } else {
-                            std::thread::sleep(std::time::Duration::from_millis(150));
-                            if let Err(e) = main_window_clone.set_focus() {
-                                eprintln!("Failed to set focus after fullscreen: {}", e);
-                            }
-                            main_window_clone.emit("macos-activation-policy-accessory-done", "").unwrap();
-                        }
-                    }
-                }
-            });
-
-            // main_window.on_window_event()
-
-            let app_handle_2 = app.handle().clone();
-            main_window.listen("macos-activation-policy-regular", move |_| {
-                println!("EVENT macos-activation-policy-regular");
-                #[cfg(target_os = "macos")]
-                app_handle_2
-                    .set_activation_policy(tauri::ActivationPolicy::Regular)
-                    .unwrap();
-            });
-
-            Ok(())
-        })
+        .plugin(tauri_plugin_opener::init())
+        .manage(LogBuffer::new(LOG_BUFFER_CAPACITY))
+        .manage(StartupTracker::default())
+        .invoke_handler(tauri::generate_handler![
+            commands::server_start,
+            commands::server_stop,
+            commands::server_restart,
+            commands::server_status,
+            commands::get_last_crash,
+            commands::get_startup_progress,
+            commands::get_server_url,
+            commands::get_log_dir,
+            commands::get_server_logs,
+            commands::get_desktop_settings,
+            commands::set_desktop_settings,
+            commands::get_profiles,
+            commands::switch_profile,
+            commands::connect_to_server,
+            commands::open_log_dir,
+            commands::open_data_dir,
+            commands::copy_diagnostics,
+            commands::quit_app,
+        ])
+        .on_page_load(server::on_page_load)
+        .setup(|app| Ok(setup(app)?))
         .build(tauri::generate_context!())
-        .expect("error while running tauri application")
+        .unwrap_or_else(|e| {
+            log_error!("Failed to start Seanime Desktop: {}", e);
+            std::process::exit(1);
+        })
         .run({
-            let server_process_for_exit = Arc::clone(&server_process);
-            let is_shutdown_for_exit = Arc::clone(&is_shutdown);
             move |app, event| {
-                let server_process_for_exit_ = Arc::clone(&server_process);
-                app.listen("kill-server", move |_| {
-                    let mut child_guard = server_process_for_exit_.lock().unwrap();
-                    if let Some(child) = child_guard.take() {
-                        // Kill server process
-                        if let Err(e) = child.kill() {
-                            eprintln!("Failed to kill server process: {}", e);
-                        }
-                    }
-                });
-
                 match event {
                     tauri::RunEvent::WindowEvent {
                         label,
                         event: tauri::WindowEvent::CloseRequested { api, .. },
                         ..
                     } => {
-                        let is_shutdown_guard = is_shutdown_for_exit.lock().unwrap();
-                        if label.as_str() == MAIN_WINDOW_LABEL && !*is_shutdown_guard {
-                            println!("Main window close request");
-                            // Hide the window when user clicks 'X'
-                            let win = app.get_webview_window(label.as_str()).unwrap();
-                            win.hide().unwrap();
+                        let state = app.state::<SidecarState>();
+                        // Let the window close once the server is gone for good
+                        let is_shutdown = match state.state() {
+                            ServerState::Stopped => true,
+                            ServerState::Crashed => !state.has_started(),
+                            _ => false,
+                        };
+                        // Closing the crash screen quits, like its Quit button
+                        if label.as_str() == CRASH_SCREEN_WINDOW_LABEL
+                            && state.state() == ServerState::Crashed
+                        {
+                            api.prevent_close();
+                            app.exit(0);
+                            return;
+                        }
+                        if label.as_str() == MAIN_WINDOW_LABEL && !is_shutdown {
+                            log_info!("Main window close request");
                             // Prevent the window from being closed
                             api.prevent_close();
-                            #[cfg(target_os = "macos")]
-                            app.set_activation_policy(tauri::ActivationPolicy::Accessory)
-                                .unwrap();
+                            // Hide the window when user clicks 'X'
+                            if let Err(e) = screens::hide_main_window(app) {
+                                log_error!("Failed to hide the main window: {}", e);
+                            }
                         }
                     }
 
@@ -174,15 +139,24 @@ pub fn run() {
                     // }
 
                     // The app is about to exit
-                    tauri::RunEvent::ExitRequested { .. } => {
-                        println!("Main window exit request");
-                        let mut child_guard = server_process_for_exit.lock().unwrap();
-                        if let Some(child) = child_guard.take() {
-                            // Kill server process
-                            if let Err(e) = child.kill() {
-                                eprintln!("Failed to kill server process: {}", e);
-                            }
+                    tauri::RunEvent::ExitRequested { code, api, .. } => {
+                        if EXIT_READY.load(Ordering::SeqCst) {
+                            return;
+                        }
+                        // Stop server process before the app exits, without blocking the event loop meanwhile
+                        api.prevent_exit();
+                        if STOPPING_FOR_EXIT.swap(true, Ordering::SeqCst) {
+                            return;
                         }
+                        log_info!("Main window exit request");
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = server::stop_seanime_server(&app).await {
+                                log_error!("Failed to stop server process: {}", e);
+                            }
+                            EXIT_READY.store(true, Ordering::SeqCst);
+                            app.exit(code.unwrap_or(0));
+                        });
                     }
                     _ => {}
                 }
@@ -190,4 +164,95 @@ pub fn run() {
         });
 }
 
+fn setup(app: &mut tauri::App) -> Result<(), DesktopError> {
+    let log_dir = app.path().app_log_dir()?;
+    if let Err(e) = logging::init(log_dir, logging::LogRetention::from_env()) {
+        log_error!("Failed to initialize log files: {}", e);
+    }
+    panic_hook::install(app.handle());
+
+    let settings = SettingsStore::load(app.path().app_config_dir()?.join(SETTINGS_FILE_NAME));
+    // The launch flag takes precedence over the saved server mode
+    let mode = ServerMode::from_launch_args().unwrap_or_else(|| settings.get().server_mode);
+    app.manage(settings);
+    app.manage(RuntimeFile::new(
+        app.path().app_local_data_dir()?.join(RUNTIME_FILE_NAME),
+    ));
+    app.manage(SidecarState::new(Supervisor::default(), mode));
+
+    #[cfg(desktop)]
+    {
+        let handle = app.handle();
+        tray::create_tray(handle)?;
+    }
+
+    let main_window = screens::main_window(app.handle())?;
+    main_window.hide()?;
+
+    // Set overlay title bar only when building for macOS
+    #[cfg(target_os = "macos")]
+    main_window.set_title_bar_style(TitleBarStyle::Overlay)?;
+
+    // Hide the title bar on Windows
+    #[cfg(target_os = "windows")]
+    main_window.set_decorations(false)?;
+
+    // Open dev tools only when in dev mode
+    #[cfg(debug_assertions)]
+    {
+        main_window.open_devtools();
+    }
+
+    screens::init(app.handle());
+    server::launch_seanime_server(app.handle().clone())?;
+
+    #[cfg(target_os = "macos")]
+    let app_handle_1 = app.handle().clone();
+    #[cfg(target_os = "macos")]
+    let main_window_clone = main_window.clone();
+    main_window.listen("macos-activation-policy-accessory", move |_| {
+        log_info!("EVENT macos-activation-policy-accessory");
+        #[cfg(target_os = "macos")]
+        {
+            if let Err(e) = app_handle_1.set_activation_policy(tauri::ActivationPolicy::Accessory) {
+                log_error!("Failed to set activation policy to accessory: {}", e);
+            } else {
+                if let Err(e) = main_window_clone.show() {
+                    log_error!("Failed to show main window: {}", e);
+                }
+                if let Err(e) = main_window_clone.set_fullscreen(true) {
+                    log_error!("Failed to set fullscreen: {}", e);
+                } else {
+                    std::thread::sleep(std::time::Duration::from_millis(150));
+                    if let Err(e) = main_window_clone.set_focus() {
+                        log_error!("Failed to set focus after fullscreen: {}", e);
+                    }
+                    if let Err(e) =
+                        main_window_clone.emit("macos-activation-policy-accessory-done", "")
+                    {
+                        log_error!(
+                            "Failed to emit macos-activation-policy-accessory-done event: {}",
+                            e
+                        );
+                    }
+                }
+            }
+        }
+    });
+
+    // main_window.on_window_event()
+
+    #[cfg(target_os = "macos")]
+    let app_handle_2 = app.handle().clone();
+    main_window.listen("macos-activation-policy-regular", move |_| {
+        log_info!("EVENT macos-activation-policy-regular");
+        #[cfg(target_os = "macos")]
+        if let Err(e) = app_handle_2.set_activation_policy(tauri::ActivationPolicy::Regular) {
+            log_error!("Failed to set activation policy to regular: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
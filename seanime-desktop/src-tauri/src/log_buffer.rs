@@ -0,0 +1,290 @@
+use crate::error::LockExt;
+use crate::output::OutputStream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Severity of a line of server output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    Panic,
+}
+
+impl LogLevel {
+    /// Parses the level written by the server's console logger, e.g. "INF" or "|INF|".
+    pub fn from_token(token: &str) -> Option<Self> {
+        match token.trim_matches('|') {
+            "TRC" => Some(LogLevel::Trace),
+            "DBG" => Some(LogLevel::Debug),
+            "INF" => Some(LogLevel::Info),
+            "WRN" => Some(LogLevel::Warn),
+            "ERR" => Some(LogLevel::Error),
+            "FTL" => Some(LogLevel::Fatal),
+            "PNC" => Some(LogLevel::Panic),
+            _ => None,
+        }
+    }
+}
+
+/// A line of server output kept in memory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch at which the line was received
+    pub timestamp: i64,
+    pub stream: OutputStream,
+    pub level: Option<LogLevel>,
+    pub line: String,
+}
+
+/// Filters accepted by the "get_server_logs" command. Every field is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    /// Minimum level, lines without a level are excluded when set
+    pub level: Option<LogLevel>,
+    /// Case-insensitive substring
+    pub contains: Option<String>,
+    /// Only lines received after this timestamp (milliseconds since the Unix epoch)
+    pub since: Option<i64>,
+    /// Maximum number of lines returned, the most recent ones are kept
+    pub limit: Option<usize>,
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(level) = self.level {
+            if !entry.level.is_some_and(|l| l >= level) {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if entry.timestamp <= since {
+                return false;
+            }
+        }
+        if let Some(contains) = &self.contains {
+            if !entry.line.to_lowercase().contains(&contains.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Bounded buffer of the most recent server output lines, registered with `app.manage`.
+pub struct LogBuffer {
+    capacity: usize,
+    entries: Mutex<VecDeque<LogEntry>>,
+}
+
+impl LogBuffer {
+    /// Buffer keeping the last `capacity` lines, at least one.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, stream: OutputStream, line: &str, level: Option<LogLevel>) {
+        let level = match (level, stream) {
+            (Some(level), _) => Some(level),
+            // Unformatted stderr output is usually a panic or a fatal error
+            (None, OutputStream::Stderr) => Some(LogLevel::Error),
+            (None, OutputStream::Stdout) => None,
+        };
+        let entry = LogEntry {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            stream,
+            level,
+            line: line.to_string(),
+        };
+
+        let mut entries = self.entries.lock_or_recover();
+        while entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Returns the entries matching the filter, oldest first.
+    pub fn query(&self, filter: &LogFilter) -> Vec<LogEntry> {
+        let entries = self.entries.lock_or_recover();
+        let mut matching: Vec<LogEntry> = entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .cloned()
+            .collect();
+        if let Some(limit) = filter.limit {
+            let skip = matching.len().saturating_sub(limit);
+            matching.drain(..skip);
+        }
+        matching
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(entries: &[LogEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.line.as_str()).collect()
+    }
+
+    /// Buffer with one line per level, received one second apart starting at 1000.
+    fn buffer() -> LogBuffer {
+        let buffer = LogBuffer::new(16);
+        buffer.push(OutputStream::Stdout, "plain output", None);
+        buffer.push(
+            OutputStream::Stdout,
+            "debug: Loading settings",
+            Some(LogLevel::Debug),
+        );
+        buffer.push(
+            OutputStream::Stdout,
+            "info: Server started",
+            Some(LogLevel::Info),
+        );
+        buffer.push(
+            OutputStream::Stdout,
+            "warn: Slow SETTINGS query",
+            Some(LogLevel::Warn),
+        );
+        buffer.push(OutputStream::Stderr, "stderr output", None);
+        buffer.push(
+            OutputStream::Stdout,
+            "fatal: Database locked",
+            Some(LogLevel::Fatal),
+        );
+        for (i, entry) in buffer.entries.lock_or_recover().iter_mut().enumerate() {
+            entry.timestamp = 1000 * (i as i64 + 1);
+        }
+        buffer
+    }
+
+    #[test]
+    fn evicts_the_oldest_lines() {
+        let buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(OutputStream::Stdout, &format!("line {}", i), None);
+        }
+        assert_eq!(
+            lines(&buffer.query(&LogFilter::default())),
+            ["line 2", "line 3", "line 4"]
+        );
+    }
+
+    #[test]
+    fn keeps_one_line_with_zero_capacity() {
+        let buffer = LogBuffer::new(0);
+        buffer.push(OutputStream::Stdout, "first", None);
+        buffer.push(OutputStream::Stdout, "second", None);
+        assert_eq!(lines(&buffer.query(&LogFilter::default())), ["second"]);
+    }
+
+    #[test]
+    fn treats_unformatted_stderr_as_errors() {
+        let entries = buffer().query(&LogFilter::default());
+        assert_eq!(entries[0].level, None);
+        assert_eq!(entries[4].level, Some(LogLevel::Error));
+    }
+
+    #[test]
+    fn filters_by_level() {
+        let filter = LogFilter {
+            level: Some(LogLevel::Warn),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            [
+                "warn: Slow SETTINGS query",
+                "stderr output",
+                "fatal: Database locked"
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_by_substring() {
+        let filter = LogFilter {
+            contains: Some("settings".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            ["debug: Loading settings", "warn: Slow SETTINGS query"]
+        );
+    }
+
+    #[test]
+    fn filters_by_timestamp() {
+        let filter = LogFilter {
+            since: Some(4000),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            ["stderr output", "fatal: Database locked"]
+        );
+    }
+
+    #[test]
+    fn limits_to_the_most_recent_lines() {
+        let filter = LogFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            ["stderr output", "fatal: Database locked"]
+        );
+
+        let filter = LogFilter {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert!(buffer().query(&filter).is_empty());
+    }
+
+    #[test]
+    fn combines_filters() {
+        let filter = LogFilter {
+            level: Some(LogLevel::Debug),
+            contains: Some("o".to_string()),
+            since: Some(2000),
+            limit: Some(2),
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            ["stderr output", "fatal: Database locked"]
+        );
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Debug),
+            contains: Some("settings".to_string()),
+            since: Some(2000),
+            limit: Some(5),
+        };
+        assert_eq!(
+            lines(&buffer().query(&filter)),
+            ["warn: Slow SETTINGS query"]
+        );
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Info),
+            contains: Some("output".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(lines(&buffer().query(&filter)), ["stderr output"]);
+    }
+}
@@ -0,0 +1,382 @@
+use crate::constants::{
+    LOG_MAX_AGE_DAYS, LOG_MAX_FILES, LOG_MAX_FILE_SIZE, LOG_ROTATION_INTERVAL_SECS,
+};
+use crate::error::LockExt;
+use crate::output::OutputStream;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+pub const DESKTOP_LOG_NAME: &str = "desktop";
+pub const SIDECAR_LOG_NAME: &str = "sidecar";
+
+/// Prints a message and writes it to the desktop log file.
+macro_rules! log_info {
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        println!("{}", message);
+        $crate::logging::write_desktop("INF", &message);
+    }};
+}
+
+/// Prints an error and writes it to the desktop log file.
+macro_rules! log_error {
+    ($($arg:tt)*) => {{
+        let message = format!($($arg)*);
+        eprintln!("{}", message);
+        $crate::logging::write_desktop("ERR", &message);
+    }};
+}
+
+pub(crate) use log_error;
+pub(crate) use log_info;
+
+/// How much log history is kept on disk.
+#[derive(Debug, Clone)]
+pub struct LogRetention {
+    /// Size after which the current file is rotated
+    pub max_file_size: u64,
+    /// Number of rotated files kept next to the current one
+    pub max_files: usize,
+    /// Rotated files older than this are deleted
+    pub max_age: Duration,
+}
+
+impl Default for LogRetention {
+    fn default() -> Self {
+        Self {
+            max_file_size: LOG_MAX_FILE_SIZE,
+            max_files: LOG_MAX_FILES,
+            max_age: Duration::from_secs(LOG_MAX_AGE_DAYS * 24 * 60 * 60),
+        }
+    }
+}
+
+impl LogRetention {
+    /// Default retention, overridable through `SEANIME_DESKTOP_LOG_MAX_FILE_SIZE_MB`,
+    /// `SEANIME_DESKTOP_LOG_MAX_FILES` and `SEANIME_DESKTOP_LOG_MAX_AGE_DAYS`.
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// Default retention with the overrides found through `var`. Values that don't parse are ignored.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut retention = Self::default();
+        if let Some(mb) = var("SEANIME_DESKTOP_LOG_MAX_FILE_SIZE_MB")
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|mb| *mb > 0)
+        {
+            retention.max_file_size = mb * 1024 * 1024;
+        }
+        if let Some(max_files) =
+            var("SEANIME_DESKTOP_LOG_MAX_FILES").and_then(|s| s.parse::<usize>().ok())
+        {
+            retention.max_files = max_files;
+        }
+        if let Some(days) =
+            var("SEANIME_DESKTOP_LOG_MAX_AGE_DAYS").and_then(|s| s.parse::<u64>().ok())
+        {
+            retention.max_age = Duration::from_secs(days * 24 * 60 * 60);
+        }
+        retention
+    }
+}
+
+/// Log file that is rotated once it grows past the size limit or once it's been written to for a day.
+/// Rotated files are named `<name>.1.log` (newest) to `<name>.<max_files>.log` (oldest).
+pub struct RotatingFile {
+    dir: PathBuf,
+    name: &'static str,
+    retention: LogRetention,
+    file: Option<File>,
+    size: u64,
+    opened_at: SystemTime,
+}
+
+impl RotatingFile {
+    pub fn new(dir: &Path, name: &'static str, retention: LogRetention) -> Self {
+        let file = Self {
+            dir: dir.to_path_buf(),
+            name,
+            retention,
+            file: None,
+            size: 0,
+            opened_at: SystemTime::now(),
+        };
+        file.prune();
+        file
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(format!("{}.log", self.name))
+        } else {
+            self.dir.join(format!("{}.{}.log", self.name, index))
+        }
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(0))?;
+        let metadata = file.metadata()?;
+        self.size = metadata.len();
+        self.opened_at = metadata.created().unwrap_or_else(|_| SystemTime::now());
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        if self.retention.max_files == 0 {
+            fs::remove_file(self.path(0))?;
+            return self.open();
+        }
+        let _ = fs::remove_file(self.path(self.retention.max_files));
+        for index in (0..self.retention.max_files).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(from, self.path(index + 1))?;
+            }
+        }
+        self.prune();
+        self.open()
+    }
+
+    /// Deletes the rotated files that are past the retention age.
+    fn prune(&self) {
+        for index in 1..=self.retention.max_files {
+            let path = self.path(index);
+            let is_expired = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map(|age| age > self.retention.max_age)
+                .unwrap_or(false);
+            if is_expired {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    pub fn write_line(&mut self, line: &str) {
+        if self.file.is_none() && self.open().is_err() {
+            return;
+        }
+
+        let len = line.len() as u64 + 1;
+        let is_full = self.size > 0 && self.size + len > self.retention.max_file_size;
+        let is_old = self
+            .opened_at
+            .elapsed()
+            .map(|age| age > Duration::from_secs(LOG_ROTATION_INTERVAL_SECS))
+            .unwrap_or(false);
+        if is_full || is_old {
+            if let Err(e) = self.rotate() {
+                eprintln!("Failed to rotate {} log file: {}", self.name, e);
+                return;
+            }
+        }
+
+        if let Some(file) = &mut self.file {
+            if writeln!(file, "{}", line).is_ok() {
+                self.size += len;
+            }
+        }
+    }
+}
+
+struct DesktopLogger {
+    dir: PathBuf,
+    desktop: Mutex<RotatingFile>,
+    sidecar: Mutex<RotatingFile>,
+}
+
+static LOGGER: OnceLock<DesktopLogger> = OnceLock::new();
+
+/// Starts writing the desktop and sidecar logs to `dir`.
+pub fn init(dir: PathBuf, retention: LogRetention) -> io::Result<()> {
+    fs::create_dir_all(&dir)?;
+    let logger = DesktopLogger {
+        desktop: Mutex::new(RotatingFile::new(&dir, DESKTOP_LOG_NAME, retention.clone())),
+        sidecar: Mutex::new(RotatingFile::new(&dir, SIDECAR_LOG_NAME, retention)),
+        dir,
+    };
+    let _ = LOGGER.set(logger);
+    Ok(())
+}
+
+/// Directory the log files are written to, once logging is initialized.
+pub fn log_dir() -> Option<PathBuf> {
+    LOGGER.get().map(|logger| logger.dir.clone())
+}
+
+fn timestamp() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn write_desktop(level: &str, message: &str) {
+    if let Some(logger) = LOGGER.get() {
+        let line = format!("{} |{}| {}", timestamp(), level, message);
+        logger.desktop.lock_or_recover().write_line(&line);
+    }
+}
+
+/// Writes a line of server output. The server already timestamps its stdout.
+pub fn write_sidecar(stream: OutputStream, line: &str) {
+    if let Some(logger) = LOGGER.get() {
+        let mut file = logger.sidecar.lock_or_recover();
+        match stream {
+            OutputStream::Stdout => file.write_line(line),
+            OutputStream::Stderr => file.write_line(&format!("{} [stderr] {}", timestamp(), line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for the log files of a test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("seanime-logging-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn retention(max_file_size: u64, max_files: usize, max_age: Duration) -> LogRetention {
+        LogRetention {
+            max_file_size,
+            max_files,
+            max_age,
+        }
+    }
+
+    fn log_files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn rotates_once_the_file_is_full() {
+        let dir = temp_dir("size");
+        let mut file = RotatingFile::new(&dir, "test", retention(10, 5, DAY));
+        file.write_line("first");
+        file.write_line("second");
+        file.write_line("third");
+
+        assert_eq!(log_files(&dir), ["test.1.log", "test.2.log", "test.log"]);
+        assert_eq!(fs::read_to_string(dir.join("test.log")).unwrap(), "third\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("test.1.log")).unwrap(),
+            "second\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("test.2.log")).unwrap(),
+            "first\n"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn keeps_at_most_max_files() {
+        let dir = temp_dir("count");
+        let mut file = RotatingFile::new(&dir, "test", retention(10, 2, DAY));
+        for i in 0..6 {
+            file.write_line(&format!("line {}", i));
+        }
+
+        assert_eq!(log_files(&dir), ["test.1.log", "test.2.log", "test.log"]);
+        assert_eq!(
+            fs::read_to_string(dir.join("test.2.log")).unwrap(),
+            "line 3\n"
+        );
+
+        // Without rotated files, the current file is started over
+        let mut file = RotatingFile::new(&dir, "other", retention(10, 0, DAY));
+        file.write_line("first");
+        file.write_line("second");
+        assert_eq!(
+            fs::read_to_string(dir.join("other.log")).unwrap(),
+            "second\n"
+        );
+        assert!(!dir.join("other.1.log").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn deletes_expired_files() {
+        let dir = temp_dir("age");
+        for name in ["test.1.log", "test.2.log"] {
+            fs::write(dir.join(name), "old\n").unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+
+        // Rotated files are pruned when the log is opened and on every rotation
+        let kept = RotatingFile::new(&dir, "test", retention(10, 5, DAY));
+        drop(kept);
+        assert_eq!(log_files(&dir), ["test.1.log", "test.2.log"]);
+
+        let mut file = RotatingFile::new(&dir, "test", retention(10, 5, Duration::from_millis(10)));
+        assert!(log_files(&dir).is_empty());
+        file.write_line("first");
+        std::thread::sleep(Duration::from_millis(20));
+        file.write_line("second");
+        assert_eq!(log_files(&dir), ["test.log"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reads_retention_from_env() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let retention = LogRetention::from_vars(vars(&[
+            ("SEANIME_DESKTOP_LOG_MAX_FILE_SIZE_MB", "2"),
+            ("SEANIME_DESKTOP_LOG_MAX_FILES", "0"),
+            ("SEANIME_DESKTOP_LOG_MAX_AGE_DAYS", "3"),
+        ]));
+        assert_eq!(retention.max_file_size, 2 * 1024 * 1024);
+        assert_eq!(retention.max_files, 0);
+        assert_eq!(retention.max_age, 3 * DAY);
+
+        let defaults = LogRetention::default();
+        for value in ["", "abc", "-1", "1.5", "0"] {
+            let retention = LogRetention::from_vars(|key| match key {
+                "SEANIME_DESKTOP_LOG_MAX_FILE_SIZE_MB" => Some(value.to_string()),
+                _ => None,
+            });
+            assert_eq!(
+                retention.max_file_size, defaults.max_file_size,
+                "max file size {:?}",
+                value
+            );
+        }
+        for value in ["", "abc", "-1", "1.5"] {
+            let retention = LogRetention::from_vars(|_| Some(value.to_string()));
+            assert_eq!(
+                retention.max_files, defaults.max_files,
+                "max files {:?}",
+                value
+            );
+            assert_eq!(retention.max_age, defaults.max_age, "max age {:?}", value);
+        }
+    }
+}
@@ -1,22 +1,6 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::Manager;
-
 fn main() {
-    #[cfg(target_os = "linux")]
-    {
-        std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
-    }
-
-    tauri::Builder::default()
-        .setup(|app| {
-            let app_handle = app.handle();
-            app.listen("tauri://close-requested", move |_| {
-                app_handle.exit(0); // Fully quits the app
-            });
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running Tauri application");
+    app_lib::run();
 }
@@ -0,0 +1,52 @@
+use crate::logging::log_error;
+use serde::{Deserialize, Serialize};
+
+/// Where the desktop app gets its server from.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", content = "url", rename_all = "camelCase")]
+pub enum ServerMode {
+    /// Spawn and supervise the bundled sidecar
+    #[default]
+    Sidecar,
+    /// Connect to a server that is already running at this base URL
+    Remote(String),
+}
+
+impl ServerMode {
+    /// Reads the `--server-url <url>` launch flag, or `SEANIME_DESKTOP_SERVER_URL`.
+    /// Returns None if neither is set or the URL is invalid, in which case the desktop settings apply.
+    pub fn from_launch_args() -> Option<Self> {
+        let mut args = std::env::args().skip(1);
+        let mut url = None;
+        while let Some(arg) = args.next() {
+            if arg == "--server-url" {
+                url = args.next();
+            } else if let Some(value) = arg.strip_prefix("--server-url=") {
+                url = Some(value.to_string());
+            }
+        }
+        let url = url
+            .or_else(|| std::env::var("SEANIME_DESKTOP_SERVER_URL").ok())
+            .filter(|url| !url.trim().is_empty())?;
+
+        match parse_server_url(&url) {
+            Ok(url) => Some(ServerMode::Remote(url)),
+            Err(e) => {
+                log_error!("Ignoring server URL {:?}: {}", url, e);
+                None
+            }
+        }
+    }
+}
+
+/// Validates a server URL and returns it without a trailing slash.
+pub fn parse_server_url(url: &str) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| e.to_string())?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(format!("unsupported scheme {:?}", parsed.scheme()));
+    }
+    if parsed.host_str().is_none() {
+        return Err("missing host".to_string());
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
@@ -0,0 +1,275 @@
+use crate::health;
+use crate::logging::{log_error, log_info};
+use crate::settings::SettingsStore;
+use crate::shutdown;
+use crate::state::SidecarState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const RUNTIME_FILE_NAME: &str = "sidecar.json";
+
+/// Identity of a running process, used to tell our sidecar apart from a process that reused its PID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessIdentity {
+    pub executable: String,
+    /// Start time as reported by the OS, in an OS-specific format
+    pub start_time: String,
+}
+
+/// The sidecar of the current session, written to the runtime file while it runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarRecord {
+    pub pid: u32,
+    pub port: u16,
+    pub profile: String,
+    /// Milliseconds since the Unix epoch at which the sidecar was spawned
+    pub started_at: i64,
+    pub identity: ProcessIdentity,
+    /// Token the sidecar accepts shutdown requests with, missing in records of older versions
+    #[serde(default)]
+    pub shutdown_token: String,
+}
+
+/// What to do with a sidecar left running by a previous session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrphanPolicy {
+    Terminate,
+    /// Keep using it if it belongs to the active profile and is healthy, terminate it otherwise
+    Adopt,
+}
+
+impl OrphanPolicy {
+    /// Reads `SEANIME_DESKTOP_ORPHAN_POLICY` ("terminate" or "adopt"), terminating by default.
+    pub fn from_env() -> Self {
+        match std::env::var("SEANIME_DESKTOP_ORPHAN_POLICY").as_deref() {
+            Ok("adopt") => OrphanPolicy::Adopt,
+            _ => OrphanPolicy::Terminate,
+        }
+    }
+}
+
+pub enum OrphanOutcome {
+    /// No sidecar from a previous session is running
+    None,
+    Terminated,
+    /// The orphan is now the server of this session
+    Adopted,
+}
+
+/// Runtime file recording the running sidecar, registered with `app.manage`.
+pub struct RuntimeFile {
+    path: PathBuf,
+}
+
+impl RuntimeFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn read(&self) -> Option<SidecarRecord> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log_error!("Ignoring invalid sidecar runtime file: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Records a newly spawned sidecar.
+    pub fn write(&self, pid: u32, port: u16, profile: &str) {
+        let Some(identity) = process_identity(pid) else {
+            log_error!(
+                "Could not identify server process {}, it won't be recorded",
+                pid
+            );
+            return;
+        };
+        let record = SidecarRecord {
+            pid,
+            port,
+            profile: profile.to_string(),
+            started_at: chrono::Utc::now().timestamp_millis(),
+            identity,
+            shutdown_token: shutdown::session_token().to_string(),
+        };
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| serde_json::to_vec_pretty(&record).map_err(io::Error::from))
+            .and_then(|json| fs::write(&self.path, json));
+        if let Err(e) = result {
+            log_error!("Failed to write sidecar runtime file: {}", e);
+        }
+    }
+
+    /// Removes the record if it is about the process identified by `pid`.
+    pub fn clear_if(&self, pid: u32) {
+        if self.read().is_some_and(|record| record.pid == pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn clear(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Deals with a sidecar left running by a previous session, before a new one is spawned.
+pub async fn handle_orphan(app: &AppHandle, policy: OrphanPolicy) -> OrphanOutcome {
+    let runtime = app.state::<RuntimeFile>();
+    let Some(record) = runtime.read() else {
+        return OrphanOutcome::None;
+    };
+
+    // Only touch the process if it is still the one we spawned
+    match process_identity(record.pid) {
+        Some(identity) if identity == record.identity && is_sidecar_binary(&identity) => {}
+        Some(_) => {
+            log_info!(
+                "PID {} of the previous server was reused by another process",
+                record.pid
+            );
+            runtime.clear();
+            return OrphanOutcome::None;
+        }
+        None => {
+            runtime.clear();
+            return OrphanOutcome::None;
+        }
+    }
+
+    log_info!(
+        "Found server process {} left running by a previous session (profile {:?}, port {})",
+        record.pid,
+        record.profile,
+        record.port
+    );
+
+    if policy == OrphanPolicy::Adopt {
+        let active_profile = app.state::<SettingsStore>().get().active().name.clone();
+        let base_url = health::server_base_url(record.port);
+        let is_healthy = match reqwest::Client::builder()
+            .timeout(Duration::from_secs(2))
+            .build()
+        {
+            Ok(client) => health::check_status(&client, &base_url).await,
+            Err(_) => false,
+        };
+        if record.profile == active_profile && is_healthy {
+            log_info!("Adopting server process {}", record.pid);
+            let state = app.state::<SidecarState>();
+            state.set_port(record.port);
+            state.set_adopted(Some(record.pid));
+            return OrphanOutcome::Adopted;
+        }
+    }
+
+    log_info!("Terminating server process {}", record.pid);
+    let base_url = health::server_base_url(record.port);
+    shutdown::terminate_pid(
+        record.pid,
+        &base_url,
+        &record.shutdown_token,
+        shutdown::drain_timeout(),
+    )
+    .await;
+    runtime.clear();
+    OrphanOutcome::Terminated
+}
+
+/// Whether the executable is the bundled server binary: "seanime", or e.g. "seanime-x86_64-unknown-linux-gnu" in development.
+fn is_sidecar_binary(identity: &ProcessIdentity) -> bool {
+    let Some(name) = std::path::Path::new(&identity.executable).file_stem() else {
+        return false;
+    };
+    let name = name.to_string_lossy().to_lowercase();
+    match name.strip_prefix("seanime") {
+        Some("") => true,
+        // Target triple suffix, which rules out other executables such as seanime-desktop
+        Some(suffix) => suffix
+            .strip_prefix('-')
+            .is_some_and(|triple| triple.starts_with(std::env::consts::ARCH)),
+        None => false,
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    let executable = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+    let executable = executable.to_string_lossy();
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, the fields after it don't.
+    // The start time is the 22nd field, i.e. the 20th after the command name.
+    let start_time = stat.rsplit_once(')')?.1.split_whitespace().nth(19)?;
+    Some(ProcessIdentity {
+        executable: executable.trim_end_matches(" (deleted)").to_string(),
+        start_time: start_time.to_string(),
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    let ps = |field: &str| -> Option<String> {
+        let output = std::process::Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", field])
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    };
+    Some(ProcessIdentity {
+        executable: ps("comm=")?,
+        start_time: ps("lstart=")?,
+    })
+}
+
+#[cfg(windows)]
+pub fn process_identity(pid: u32) -> Option<ProcessIdentity> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 {
+            return None;
+        }
+
+        let mut path = [0u16; 1024];
+        let mut len = path.len() as u32;
+        let has_path =
+            QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, path.as_mut_ptr(), &mut len)
+                != 0;
+
+        let empty = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+        let has_times =
+            GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) != 0;
+        CloseHandle(handle);
+
+        if !has_path || !has_times {
+            return None;
+        }
+        // The creation time tells the process apart from a later one that reused its PID
+        let start_time =
+            (u64::from(creation.dwHighDateTime) << 32) | u64::from(creation.dwLowDateTime);
+        Some(ProcessIdentity {
+            executable: String::from_utf16_lossy(&path[..len as usize]),
+            start_time: start_time.to_string(),
+        })
+    }
+}
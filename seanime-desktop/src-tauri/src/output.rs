@@ -0,0 +1,31 @@
+use crate::zerolog::ParsedLog;
+use serde::{Deserialize, Serialize};
+
+pub const SERVER_LOG_EVENT: &str = "server-log";
+
+/// Stream a line of server output was written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Payload of the "server-log" event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerLogEvent {
+    pub stream: OutputStream,
+    #[serde(flatten)]
+    pub log: ParsedLog,
+    /// Line as written by the server, without colors
+    pub line: String,
+}
+
+/// Strips the color codes from a line of server output.
+pub fn decode_line(line: Vec<u8>) -> String {
+    let line_without_colors = strip_ansi_escapes::strip(line);
+    String::from_utf8_lossy(&line_without_colors)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
@@ -0,0 +1,109 @@
+use crate::constants::{
+    CRASH_REPORT_TIMEOUT_MS, CRASH_SCREEN_TIMEOUT_SECS, SHUTDOWN_REQUEST_TIMEOUT_MS,
+};
+use crate::diagnosis::{CrashCategory, CrashDiagnosis};
+use crate::logging::{log_error, log_info};
+use crate::report;
+use crate::screens;
+use crate::shutdown;
+use crate::state::SidecarState;
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::panic::{self, Location};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Set once a panic is being handled, panics happening meanwhile are only printed
+static HANDLING_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// Reports panics of the desktop app: a crash report is written to the log directory and the crash screen
+/// is shown. The server is stopped and the app exits once the crash screen is dismissed,
+/// or right away if it can't be shown.
+pub fn install(app: &AppHandle) {
+    let app = app.clone();
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if HANDLING_PANIC.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let panic = format!(
+            "{}\n\n{}",
+            describe(info.payload(), info.location()),
+            Backtrace::force_capture()
+        );
+        let on_main_thread = thread::current().name() == Some("main");
+        let (tx, rx) = mpsc::channel();
+        let app = app.clone();
+        // The panicking thread may hold locks the report needs, which are only released once it unwinds.
+        // The report is made on another thread and only waited for a limited time.
+        let spawned = thread::Builder::new()
+            .name("crash-reporter".to_string())
+            .spawn(move || report_panic(&app, panic, on_main_thread, tx));
+        if spawned.is_ok() {
+            let _ = rx.recv_timeout(Duration::from_millis(CRASH_REPORT_TIMEOUT_MS));
+        }
+    }));
+}
+
+fn report_panic(app: &AppHandle, panic: String, on_main_thread: bool, written: mpsc::Sender<()>) {
+    log_error!("Seanime Desktop panicked: {}", panic);
+    match report::write_crash_report(app, &panic) {
+        Ok(path) => log_info!("Crash report written to {}", path.display()),
+        Err(e) => log_error!("Failed to write the crash report: {}", e),
+    }
+    let _ = written.send(());
+
+    // The event loop runs on the main thread, nothing can be shown once it panicked
+    if on_main_thread {
+        return;
+    }
+
+    let mut diagnosis = CrashDiagnosis::new(CrashCategory::DesktopPanic);
+    diagnosis.evidence = panic.lines().next().map(str::to_string);
+    app.state::<SidecarState>()
+        .mark_desktop_crashed(app, diagnosis.explanation.clone(), diagnosis);
+
+    let deadline = Instant::now() + Duration::from_secs(CRASH_SCREEN_TIMEOUT_SECS);
+    while !screens::crash_screen_visible(app) {
+        if Instant::now() >= deadline {
+            // A sidecar left behind is cleaned up by the next session, see [crate::orphan]
+            log_error!("The crash screen could not be shown, exiting");
+            std::process::exit(1);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    // The app can't be trusted after a panic, it exits however the crash screen goes away
+    while screens::crash_screen_visible(app) {
+        thread::sleep(Duration::from_millis(100));
+    }
+    log_info!("Crash screen dismissed, exiting");
+    // Stops the server before exiting, see the ExitRequested handler in [crate::run]
+    app.exit(1);
+    // Leaves the server time to drain, the shutdown request and the kill come on top of it
+    thread::sleep(
+        shutdown::drain_timeout()
+            + Duration::from_millis(SHUTDOWN_REQUEST_TIMEOUT_MS)
+            + Duration::from_secs(1),
+    );
+    log_error!("The app did not exit after the crash, exiting now");
+    std::process::exit(1);
+}
+
+/// Panic message along with where it happened.
+fn describe(payload: &(dyn Any + Send), location: Option<&Location>) -> String {
+    let payload = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    match location {
+        Some(location) => format!("{} at {}:{}", payload, location.file(), location.line()),
+        None => payload,
+    }
+}
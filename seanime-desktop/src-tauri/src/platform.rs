@@ -0,0 +1,38 @@
+/// Platform-specific setup done before the app starts, while the process is still single-threaded.
+#[derive(Debug, Clone)]
+pub struct PlatformConfig {
+    /// Disables WebKitGTK's accelerated compositing on Linux, which renders blank windows with some GPU drivers
+    pub disable_compositing: bool,
+}
+
+impl Default for PlatformConfig {
+    fn default() -> Self {
+        Self {
+            disable_compositing: true,
+        }
+    }
+}
+
+impl PlatformConfig {
+    /// Default config, overridable through `SEANIME_DESKTOP_DISABLE_COMPOSITING` ("true" or "false").
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(disable) = std::env::var("SEANIME_DESKTOP_DISABLE_COMPOSITING")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+        {
+            config.disable_compositing = disable;
+        }
+        config
+    }
+}
+
+/// Applies `config`. Must be called before any thread is started since it sets environment variables.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+pub fn init(config: &PlatformConfig) {
+    // A value set by the user wins
+    #[cfg(target_os = "linux")]
+    if config.disable_compositing && std::env::var_os("WEBKIT_DISABLE_COMPOSITING_MODE").is_none() {
+        std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
+    }
+}
@@ -0,0 +1,170 @@
+use crate::constants::{DEFAULT_SERVER_PORT, SERVER_HOST};
+use std::io;
+use std::net::TcpListener;
+
+/// How the port of the sidecar is chosen before each launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortStrategy {
+    /// Use the preferred port, or any free port if it is taken
+    Fallback,
+    /// Use the preferred port and fail if it is taken
+    Fixed,
+    /// Always use a free port assigned by the OS
+    Random,
+}
+
+impl PortStrategy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fallback" => Some(PortStrategy::Fallback),
+            "fixed" => Some(PortStrategy::Fixed),
+            "random" => Some(PortStrategy::Random),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PortConfig {
+    pub preferred: u16,
+    pub strategy: PortStrategy,
+}
+
+impl Default for PortConfig {
+    fn default() -> Self {
+        Self {
+            preferred: DEFAULT_SERVER_PORT,
+            strategy: PortStrategy::Fallback,
+        }
+    }
+}
+
+impl PortConfig {
+    /// Default config, with the preferred port read from `SEANIME_SERVER_PORT` like the server does
+    /// and the strategy from `SEANIME_DESKTOP_PORT_STRATEGY` ("fallback", "fixed" or "random").
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(port) = std::env::var("SEANIME_SERVER_PORT")
+            .ok()
+            .and_then(|p| p.parse::<u16>().ok())
+        {
+            config.preferred = port;
+        }
+        if let Some(strategy) = std::env::var("SEANIME_DESKTOP_PORT_STRATEGY")
+            .ok()
+            .and_then(|s| PortStrategy::parse(&s))
+        {
+            config.strategy = strategy;
+        }
+        config
+    }
+}
+
+#[derive(Debug)]
+pub enum PortError {
+    /// The port is held by another process and the strategy doesn't allow another one
+    Busy(u16),
+    /// No free port could be obtained from the OS
+    Io(io::Error),
+}
+
+impl std::fmt::Display for PortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortError::Busy(port) => write!(f, "port {} is already in use", port),
+            PortError::Io(e) => write!(f, "could not find a free port: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PortError {}
+
+/// Returns true if nothing is listening on the loopback port.
+pub fn is_port_free(port: u16) -> bool {
+    TcpListener::bind((SERVER_HOST, port)).is_ok()
+}
+
+/// Asks the OS for a free loopback port.
+pub fn free_port() -> io::Result<u16> {
+    let listener = TcpListener::bind((SERVER_HOST, 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Picks the port the sidecar should listen on.
+pub fn select_port(config: &PortConfig) -> Result<u16, PortError> {
+    if config.strategy != PortStrategy::Random && is_port_free(config.preferred) {
+        return Ok(config.preferred);
+    }
+    match config.strategy {
+        PortStrategy::Fixed => Err(PortError::Busy(config.preferred)),
+        _ => free_port().map_err(PortError::Io),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Holds a loopback port for the duration of a test.
+    fn busy_port() -> (TcpListener, u16) {
+        let listener = TcpListener::bind((SERVER_HOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    fn config(preferred: u16, strategy: PortStrategy) -> PortConfig {
+        PortConfig {
+            preferred,
+            strategy,
+        }
+    }
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!(
+            PortStrategy::parse(" Fallback "),
+            Some(PortStrategy::Fallback)
+        );
+        assert_eq!(PortStrategy::parse("fixed"), Some(PortStrategy::Fixed));
+        assert_eq!(PortStrategy::parse("RANDOM"), Some(PortStrategy::Random));
+        assert_eq!(PortStrategy::parse("other"), None);
+    }
+
+    #[test]
+    fn uses_the_preferred_port_when_free() {
+        let port = free_port().unwrap();
+        assert_eq!(
+            select_port(&config(port, PortStrategy::Fixed)).unwrap(),
+            port
+        );
+        assert_eq!(
+            select_port(&config(port, PortStrategy::Fallback)).unwrap(),
+            port
+        );
+    }
+
+    #[test]
+    fn fixed_fails_when_the_port_is_busy() {
+        let (_listener, port) = busy_port();
+        assert!(!is_port_free(port));
+        match select_port(&config(port, PortStrategy::Fixed)) {
+            Err(PortError::Busy(busy)) => assert_eq!(busy, port),
+            other => panic!("expected a busy port error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fallback_picks_another_port_when_busy() {
+        let (_listener, port) = busy_port();
+        let selected = select_port(&config(port, PortStrategy::Fallback)).unwrap();
+        assert_ne!(selected, port);
+        assert!(is_port_free(selected));
+    }
+
+    #[test]
+    fn random_ignores_the_preferred_port() {
+        let (_listener, port) = busy_port();
+        let selected = select_port(&config(port, PortStrategy::Random)).unwrap();
+        assert_ne!(selected, port);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::error::LockExt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri_plugin_shell::process::{CommandEvent, TerminatedPayload};
+use tokio::sync::mpsc;
+
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+/// Time given to the output readers to forward the last lines once the process has exited
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Shell script run by the guardian. Its stdin is a pipe whose write end only this process holds,
+/// so it reaches EOF when the pipe is closed on purpose or when this process dies, however it dies.
+/// It then kills the process group it shares with the sidecar, including itself.
+const GUARDIAN_SCRIPT: &str = "while read -r _; do :; done; kill -KILL 0";
+
+/// Sidecar spawned in its own process group, along with a guardian process in that group.
+/// The whole group, including the processes the sidecar started, is killed once this handle is dropped
+/// or once the desktop process dies.
+pub struct GroupChild {
+    pid: u32,
+    /// Closing it makes the guardian kill the group
+    _guardian_stdin: ChildStdin,
+}
+
+impl GroupChild {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Kills the sidecar and every process in its group right away.
+    pub fn kill(self) -> io::Result<()> {
+        // The process group id is the sidecar's pid
+        if unsafe { libc::kill(-(self.pid as libc::pid_t), libc::SIGKILL) } != 0 {
+            let e = io::Error::last_os_error();
+            // The group is already gone
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct SpawnRequest {
+    command: Command,
+    reply: std_mpsc::Sender<io::Result<(Child, Child)>>,
+}
+
+/// Sender to the spawner thread, started by the first spawn
+static SPAWNER: Mutex<Option<std_mpsc::Sender<SpawnRequest>>> = Mutex::new(None);
+
+/// Sends the request to the thread that spawns every sidecar.
+/// The parent-death signal fires when the thread that spawned the process exits, not the whole process,
+/// so spawning from a short-lived or pooled thread would kill the sidecar too early.
+fn spawn_on_spawner_thread(command: Command) -> io::Result<(Child, Child)> {
+    let spawner = {
+        let mut spawner = SPAWNER.lock_or_recover();
+        match spawner.as_ref() {
+            Some(tx) => tx.clone(),
+            None => {
+                let (tx, rx) = std_mpsc::channel::<SpawnRequest>();
+                thread::Builder::new()
+                    .name("sidecar-spawner".to_string())
+                    .spawn(move || {
+                        for request in rx {
+                            let _ = request.reply.send(spawn_group(request.command));
+                        }
+                    })?;
+                spawner.insert(tx).clone()
+            }
+        }
+    };
+
+    let (reply_tx, reply_rx) = std_mpsc::channel();
+    let request = SpawnRequest {
+        command,
+        reply: reply_tx,
+    };
+    let unavailable = || {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "sidecar spawner thread is not running",
+        )
+    };
+    spawner.send(request).map_err(|_| unavailable())?;
+    reply_rx.recv().map_err(|_| unavailable())?
+}
+
+/// Spawns the sidecar as the leader of a new process group, then the guardian in that group.
+fn spawn_group(mut command: Command) -> io::Result<(Child, Child)> {
+    let parent = std::process::id();
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setpgid(0, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // The desktop process died before the signal was set up
+            if libc::getppid() as u32 != parent {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "parent process exited",
+                ));
+            }
+            Ok(())
+        });
+    }
+    let mut child = command.spawn()?;
+    let pgid = child.id() as libc::pid_t;
+
+    let mut guardian = Command::new("/bin/sh");
+    guardian
+        .args(["-c", GUARDIAN_SCRIPT])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    unsafe {
+        guardian.pre_exec(move || {
+            if libc::setpgid(0, pgid) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    match guardian.spawn() {
+        Ok(guardian) => Ok((child, guardian)),
+        Err(e) => {
+            unsafe { libc::kill(-pgid, libc::SIGKILL) };
+            let _ = child.wait();
+            Err(e)
+        }
+    }
+}
+
+/// Forwards each line written to `pipe` as an event.
+fn spawn_reader<R: Read + Send + 'static>(
+    pipe: R,
+    tx: mpsc::Sender<CommandEvent>,
+    wrap: fn(Vec<u8>) -> CommandEvent,
+    done: std_mpsc::Sender<()>,
+) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        loop {
+            let mut line = Vec::new();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if tx.blocking_send(wrap(line)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.blocking_send(CommandEvent::Error(e.to_string()));
+                    break;
+                }
+            }
+        }
+        let _ = done.send(());
+    });
+}
+
+/// Spawns `command` in a new process group that is torn down with the desktop process.
+/// Produces the same events as the shell plugin so the output is handled the same way.
+pub fn spawn(command: Command) -> io::Result<(mpsc::Receiver<CommandEvent>, GroupChild)> {
+    let (mut child, mut guardian) = spawn_on_spawner_thread(command)?;
+    let pid = child.id();
+    let Some(guardian_stdin) = guardian.stdin.take() else {
+        unsafe { libc::kill(-(pid as libc::pid_t), libc::SIGKILL) };
+        let _ = child.wait();
+        let _ = guardian.wait();
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "guardian stdin is not piped",
+        ));
+    };
+    let (tx, rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+
+    let (done_tx, done_rx) = std_mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, tx.clone(), CommandEvent::Stdout, done_tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, tx.clone(), CommandEvent::Stderr, done_tx);
+    }
+    thread::spawn(move || {
+        let payload = match child.wait() {
+            Ok(status) => TerminatedPayload {
+                code: status.code(),
+                signal: status.signal(),
+            },
+            Err(e) => {
+                let _ = tx.blocking_send(CommandEvent::Error(e.to_string()));
+                TerminatedPayload {
+                    code: None,
+                    signal: None,
+                }
+            }
+        };
+        // Processes started by the sidecar may keep the pipes open, don't wait for them
+        for _ in 0..2 {
+            if done_rx.recv_timeout(OUTPUT_DRAIN_TIMEOUT).is_err() {
+                break;
+            }
+        }
+        let _ = tx.blocking_send(CommandEvent::Terminated(payload));
+        drop(tx);
+        // Reap the guardian once the handle is dropped and it has killed the group
+        let _ = guardian.wait();
+    });
+
+    Ok((
+        rx,
+        GroupChild {
+            pid,
+            _guardian_stdin: guardian_stdin,
+        },
+    ))
+}
@@ -0,0 +1,223 @@
+use crate::constants::MAIN_WINDOW_LABEL;
+use crate::error::LockExt;
+use crate::logging::{log_error, log_info};
+use crate::server;
+use crate::settings::{SettingsError, SettingsStore};
+use crate::state::{InvalidTransition, ServerState, ServerStateEvent, SERVER_STATE_EVENT};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventId, Listener, Manager};
+
+pub const PROFILES_EVENT: &str = "profiles";
+
+/// Listener waiting for the server of the new profile to be ready, see [reload_main_window_when_ready]
+static PENDING_RELOAD: Mutex<Option<EventId>> = Mutex::new(None);
+
+/// Payload of the "profiles" event, also returned by the "get_profiles" command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfilesEvent {
+    pub profiles: Vec<String>,
+    pub active: String,
+}
+
+#[derive(Debug)]
+pub enum SwitchProfileError {
+    Settings(SettingsError),
+    Server(InvalidTransition),
+}
+
+impl std::fmt::Display for SwitchProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SwitchProfileError::Settings(e) => write!(f, "{}", e),
+            SwitchProfileError::Server(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SwitchProfileError {}
+
+pub fn profiles_of(app: &AppHandle) -> ProfilesEvent {
+    let settings = app.state::<SettingsStore>().get();
+    ProfilesEvent {
+        profiles: settings.profiles.iter().map(|p| p.name.clone()).collect(),
+        active: settings.active().name.clone(),
+    }
+}
+
+/// Tells the tray and the webview that the profiles changed.
+pub fn emit_profiles(app: &AppHandle) {
+    if let Err(e) = app.emit(PROFILES_EVENT, profiles_of(app)) {
+        log_error!("Failed to emit profiles event: {}", e);
+    }
+}
+
+/// Makes `name` the active profile, relaunches the server with its data directory
+/// and reloads the main window once the server is ready.
+pub async fn switch_profile(app: &AppHandle, name: &str) -> Result<(), SwitchProfileError> {
+    let settings = app.state::<SettingsStore>();
+    activate_profile(&settings, name, || async {
+        log_info!("Switching to profile {:?}", name);
+        emit_profiles(app);
+        // The web app still holds the previous profile's data
+        reload_main_window_when_ready(app);
+        let result = server::restart_seanime_server(app).await;
+        if result.is_err() {
+            cancel_reload(app);
+        }
+        result
+    })
+    .await?;
+    Ok(())
+}
+
+/// Makes `name` the active profile in `settings` and calls `restart` if it wasn't already active.
+/// Returns whether the active profile changed.
+async fn activate_profile<F, Fut>(
+    settings: &SettingsStore,
+    name: &str,
+    restart: F,
+) -> Result<bool, SwitchProfileError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<(), InvalidTransition>>,
+{
+    let changed = settings
+        .set_active_profile(name)
+        .map_err(SwitchProfileError::Settings)?;
+    if changed {
+        restart().await.map_err(SwitchProfileError::Server)?;
+    }
+    Ok(changed)
+}
+
+/// Reloads the main window the next time the server is ready, the new server may listen on another port.
+/// The reloaded page gets the server URL again from [server::on_page_load].
+fn reload_main_window_when_ready(app: &AppHandle) {
+    let app_handle = app.clone();
+    let id = app.listen(SERVER_STATE_EVENT, move |event| {
+        let Ok(payload) = serde_json::from_str::<ServerStateEvent>(event.payload()) else {
+            return;
+        };
+        if payload.state != ServerState::Ready {
+            return;
+        }
+        cancel_reload(&app_handle);
+        if let Some(main_window) = app_handle.get_webview_window(MAIN_WINDOW_LABEL) {
+            if let Err(e) = main_window.eval("window.location.reload()") {
+                log_error!("Failed to reload the main window: {}", e);
+            }
+        }
+    });
+    // Switching again before the server was ready reloads only once
+    if let Some(previous) = PENDING_RELOAD.lock_or_recover().replace(id) {
+        app.unlisten(previous);
+    }
+}
+
+fn cancel_reload(app: &AppHandle) {
+    if let Some(id) = PENDING_RELOAD.lock_or_recover().take() {
+        app.unlisten(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{CommandError, CommandErrorKind};
+    use crate::settings::{DesktopSettings, Profile};
+    use std::cell::Cell;
+    use std::path::PathBuf;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    /// Settings with the "Default" and "Work" profiles, saved under a directory unique to the test.
+    fn store(name: &str) -> (SettingsStore, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("seanime-profiles-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = SettingsStore::load(dir.join("settings.json"));
+        let mut settings = DesktopSettings::default();
+        settings.profiles.push(Profile::new("Work"));
+        store.set(settings).unwrap();
+        (store, dir)
+    }
+
+    #[test]
+    fn switches_and_restarts() {
+        let (store, dir) = store("switch");
+        let restarts = Cell::new(0);
+        let changed = block_on(activate_profile(&store, "Work", || async {
+            restarts.set(restarts.get() + 1);
+            Ok(())
+        }));
+        assert!(changed.unwrap());
+        assert_eq!(restarts.get(), 1);
+        assert_eq!(store.get().active_profile, "Work");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn does_nothing_for_the_active_profile() {
+        let (store, dir) = store("noop");
+        let active = store.get().active_profile;
+        let restarts = Cell::new(0);
+        let changed = block_on(activate_profile(&store, &active, || async {
+            restarts.set(restarts.get() + 1);
+            Ok(())
+        }));
+        assert!(!changed.unwrap());
+        assert_eq!(restarts.get(), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reports_unknown_profiles_as_invalid_settings() {
+        let (store, dir) = store("unknown");
+        let restarts = Cell::new(0);
+        let result = block_on(activate_profile(&store, "Missing", || async {
+            restarts.set(restarts.get() + 1);
+            Ok(())
+        }));
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error,
+            SwitchProfileError::Settings(SettingsError::Invalid(_))
+        ));
+        assert!(matches!(
+            CommandError::from(error).kind,
+            CommandErrorKind::InvalidSettings
+        ));
+        assert_eq!(restarts.get(), 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reports_restart_failures_as_invalid_state() {
+        let (store, dir) = store("restart");
+        let result = block_on(activate_profile(&store, "Work", || async {
+            Err(InvalidTransition {
+                from: ServerState::Stopping,
+                to: ServerState::Restarting,
+            })
+        }));
+        let error = result.unwrap_err();
+        assert!(matches!(error, SwitchProfileError::Server(_)));
+        let error = CommandError::from(error);
+        assert!(matches!(error.kind, CommandErrorKind::InvalidState));
+        assert_eq!(
+            error.message,
+            "invalid server state transition: Stopping -> Restarting"
+        );
+        // The profile stays selected, the crash screen offers to retry
+        assert_eq!(store.get().active_profile, "Work");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
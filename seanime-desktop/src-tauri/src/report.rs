@@ -0,0 +1,95 @@
+use crate::constants::DIAGNOSTICS_LOG_LINES;
+use crate::data_dir_lock;
+use crate::log_buffer::{LogBuffer, LogFilter};
+use crate::logging;
+use crate::settings::SettingsStore;
+use crate::state::SidecarState;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+/// Plain text summary of the app, the server and its last crash, meant to be attached to bug reports.
+pub fn diagnostics_summary(app: &AppHandle) -> String {
+    let state = app.state::<SidecarState>();
+    let profile = app.state::<SettingsStore>().get().active().clone();
+    let mut summary = String::new();
+
+    let _ = writeln!(
+        summary,
+        "Seanime Desktop {} ({} {})",
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        summary,
+        "Server: {} at {}{}",
+        state.state().label(),
+        state.base_url(),
+        if state.is_remote() { " (remote)" } else { "" }
+    );
+    let _ = writeln!(summary, "Profile: {}", profile.name);
+    if let Some(data_dir) = data_dir_lock::data_dir_of(app, &profile) {
+        let _ = writeln!(summary, "Data directory: {}", data_dir.display());
+    }
+    if let Some(log_dir) = logging::log_dir() {
+        let _ = writeln!(summary, "Log directory: {}", log_dir.display());
+    }
+
+    if let Some(crash) = state.last_crash() {
+        let _ = writeln!(summary, "\nLast crash:");
+        if let Some(message) = &crash.message {
+            let _ = writeln!(summary, "{}", message);
+        }
+        if let Some(diagnosis) = &crash.diagnosis {
+            let _ = writeln!(
+                summary,
+                "Diagnosis: {} ({:?}), exit code {:?}, signal {:?}",
+                diagnosis.title, diagnosis.category, diagnosis.exit_code, diagnosis.signal
+            );
+            if let Some(evidence) = &diagnosis.evidence {
+                let _ = writeln!(summary, "Cause: {}", evidence);
+            }
+        }
+        if let Some(fatal) = &crash.fatal {
+            let _ = writeln!(summary, "Fatal: {}", fatal);
+        }
+    }
+
+    let lines = app.state::<LogBuffer>().query(&LogFilter {
+        limit: Some(DIAGNOSTICS_LOG_LINES),
+        ..Default::default()
+    });
+    if !lines.is_empty() {
+        let _ = writeln!(summary, "\nRecent server output:");
+        for entry in lines {
+            let _ = writeln!(summary, "{}", entry.line);
+        }
+    }
+
+    summary
+}
+
+/// Writes a report of a panic of the desktop app to the log directory, returning its path.
+/// The panic is written first, so the report is useful even if the summary can't be gathered.
+pub fn write_crash_report(app: &AppHandle, panic: &str) -> io::Result<PathBuf> {
+    let dir = logging::log_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "logging is not initialized"))?;
+    let path = dir.join(format!(
+        "crash-{}.txt",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let mut file = File::create(&path)?;
+    writeln!(
+        file,
+        "Seanime Desktop crashed at {}\n",
+        chrono::Local::now().to_rfc3339()
+    )?;
+    writeln!(file, "{}\n", panic)?;
+    file.sync_all()?;
+    file.write_all(diagnostics_summary(app).as_bytes())?;
+    file.sync_all()?;
+    Ok(path)
+}
@@ -0,0 +1,157 @@
+use crate::constants::{CRASH_SCREEN_WINDOW_LABEL, MAIN_WINDOW_LABEL, SPLASHSCREEN_WINDOW_LABEL};
+use crate::error::DesktopError;
+use crate::logging::log_error;
+use crate::state::{ServerState, ServerStateEvent, SERVER_STATE_EVENT};
+use tauri::{AppHandle, Listener, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+/// Builds the window shown while the server starts, or returns it if it is open.
+fn splashscreen(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+    if let Some(window) = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL) {
+        return Ok(window);
+    }
+    WebviewWindowBuilder::new(
+        app,
+        SPLASHSCREEN_WINDOW_LABEL,
+        WebviewUrl::App("splashscreen".into()),
+    )
+    .title("Seanime")
+    .inner_size(800.0, 400.0)
+    .resizable(false)
+    .maximizable(false)
+    .decorations(false)
+    .center()
+    .focused(true)
+    .build()
+}
+
+/// Builds the window shown when the server fails, or returns it if it is open. It is created hidden.
+fn crash_screen(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+    if let Some(window) = app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL) {
+        return Ok(window);
+    }
+    WebviewWindowBuilder::new(
+        app,
+        CRASH_SCREEN_WINDOW_LABEL,
+        WebviewUrl::App("splashscreen/crash".into()),
+    )
+    .title("Seanime")
+    .inner_size(900.0, 640.0)
+    .min_inner_size(600.0, 400.0)
+    .resizable(true)
+    .decorations(true)
+    .center()
+    .visible(false)
+    .build()
+}
+
+fn close_splashscreen(app: &AppHandle) {
+    if let Some(splashscreen) = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL) {
+        let _ = splashscreen.close();
+    }
+}
+
+/// Hides the crash screen, returning whether it was visible.
+fn hide_crash_screen(app: &AppHandle) -> bool {
+    match app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL) {
+        Some(crash_screen) if crash_screen.is_visible().unwrap_or(false) => {
+            let _ = crash_screen.hide();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The main window, declared in the app config.
+pub fn main_window(app: &AppHandle) -> Result<WebviewWindow, DesktopError> {
+    app.get_webview_window(MAIN_WINDOW_LABEL)
+        .ok_or(DesktopError::WindowNotFound(MAIN_WINDOW_LABEL))
+}
+
+/// Shows and focuses the main window, bringing the app back to the dock on macOS.
+pub fn show_main_window(app: &AppHandle) -> Result<(), DesktopError> {
+    let window = main_window(app)?;
+    window.show()?;
+    window.set_focus()?;
+    #[cfg(target_os = "macos")]
+    app.set_activation_policy(tauri::ActivationPolicy::Regular)?;
+    Ok(())
+}
+
+/// Hides the main window, removing the app from the dock on macOS.
+pub fn hide_main_window(app: &AppHandle) -> Result<(), DesktopError> {
+    main_window(app)?.hide()?;
+    #[cfg(target_os = "macos")]
+    app.set_activation_policy(tauri::ActivationPolicy::Accessory)?;
+    Ok(())
+}
+
+/// Whether the crash screen is on screen.
+pub fn crash_screen_visible(app: &AppHandle) -> bool {
+    app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL)
+        .is_some_and(|window| window.is_visible().unwrap_or(false))
+}
+
+fn main_window_visible(app: &AppHandle) -> bool {
+    app.get_webview_window(MAIN_WINDOW_LABEL)
+        .is_some_and(|window| window.is_visible().unwrap_or(false))
+}
+
+/// Shows the window matching the new server state.
+fn on_state_change(app: &AppHandle, event: &ServerStateEvent) -> tauri::Result<()> {
+    match event.state {
+        // Give feedback until the server is ready, unless the user is already in the app
+        ServerState::Starting | ServerState::Restarting if !main_window_visible(app) => {
+            hide_crash_screen(app);
+            splashscreen(app)?.show()?;
+        }
+        ServerState::Ready => {
+            let was_starting = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL).is_some();
+            close_splashscreen(app);
+            // The server may have been retried from the crash screen
+            let was_crashed = hide_crash_screen(app);
+            if was_starting || was_crashed {
+                if let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+                    main_window.maximize()?;
+                    main_window.show()?;
+                }
+            }
+        }
+        // The supervisor shows nothing while it restarts the server on its own
+        ServerState::Crashed if !event.will_restart => {
+            close_splashscreen(app);
+            if let Some(main_window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+                main_window.hide()?;
+            }
+            let crash_screen = crash_screen(app)?;
+            crash_screen.show()?;
+            crash_screen.set_focus()?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Makes the splashscreen and the crash screen follow the server state.
+/// Must be called before the server is launched so the splashscreen appears right away.
+pub fn init(app: &AppHandle) {
+    let app_handle = app.clone();
+    app.listen(SERVER_STATE_EVENT, move |event| {
+        let Ok(payload) = serde_json::from_str::<ServerStateEvent>(event.payload()) else {
+            return;
+        };
+        // Windows are created on the main thread, in the order the states were reached
+        let app = app_handle.clone();
+        let result = app_handle.run_on_main_thread(move || {
+            if let Err(e) = on_state_change(&app, &payload) {
+                log_error!(
+                    "Failed to update windows for server state {:?}: {}",
+                    payload.state,
+                    e
+                );
+            }
+        });
+        if let Err(e) = result {
+            log_error!("Failed to update windows: {}", e);
+        }
+    });
+}
@@ -1,111 +1,220 @@
-use crate::constants::{CRASH_SCREEN_WINDOW_LABEL, MAIN_WINDOW_LABEL, SPLASHSCREEN_WINDOW_LABEL};
-use std::sync::{Arc, Mutex};
-use strip_ansi_escapes;
-use tauri::{AppHandle, Emitter, Manager};
-use tauri_plugin_shell::process::CommandEvent;
+use crate::constants::{CRASH_DIAGNOSIS_LOG_LINES, MAIN_WINDOW_LABEL, STARTUP_TIMEOUT_LOG_LINES};
+use crate::data_dir_lock::{self, DataDirConflict, DataDirLock, LockError, LockOwner};
+use crate::diagnosis::{self, CrashCategory, CrashDiagnosis};
+use crate::error::DesktopError;
+use crate::health;
+use crate::log_buffer::{LogBuffer, LogFilter};
+use crate::logging::{self, log_error, log_info};
+use crate::mode::ServerMode;
+use crate::orphan::{self, OrphanOutcome, OrphanPolicy, RuntimeFile};
+use crate::output::{self, OutputStream, ServerLogEvent, SERVER_LOG_EVENT};
+use crate::port;
+use crate::profiles;
+use crate::screens;
+use crate::settings::{Profile, SettingsStore};
+use crate::shutdown;
+use crate::sidecar;
+use crate::startup::{StartupStage, StartupTracker};
+use crate::state::{
+    CrashDetails, InvalidTransition, RestartResult, RestartTicket, ServerState, SidecarState,
+};
+use crate::watchdog;
+use crate::zerolog;
+use std::collections::BTreeMap;
+use std::future::Future;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Emitter, Manager, Webview};
+use tauri_plugin_shell::process::{Command, CommandEvent};
 use tauri_plugin_shell::ShellExt;
 use tokio::time::{sleep, Duration};
 
-pub fn launch_seanime_server(
-    app: AppHandle,
-    child_process: Arc<Mutex<Option<tauri_plugin_shell::process::CommandChild>>>,
-    is_shutdown: Arc<Mutex<bool>>,
-    server_started: Arc<Mutex<bool>>,
-) {
-    tauri::async_runtime::spawn(async move {
-        let main_window = app.get_webview_window(MAIN_WINDOW_LABEL).unwrap();
-        // let splashscreen = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL).unwrap();
-        // let crash_screen = app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL).unwrap();
+/// Spawns the sidecar and supervises it, or connects to the remote server.
+/// Callers other than the app setup should go through [start_seanime_server] so launches are serialized.
+pub fn launch_seanime_server(app: AppHandle) -> Result<(), InvalidTransition> {
+    let state = app.state::<SidecarState>();
+    state.transition(&app, ServerState::Starting, None)?;
 
-        let mut sidecar_command = app.shell().sidecar("seanime").unwrap();
+    if let ServerMode::Remote(url) = state.mode() {
+        log_info!("Connecting to the Seanime server at {}", url);
+        spawn_readiness_probe(app.clone(), None);
+        return Ok(());
+    }
 
-        // Use test data dir during development
-        #[cfg(dev)]
-        {
-            sidecar_command = sidecar_command.args(["-datadir", env!("TEST_DATADIR")]);
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<SidecarState>();
+        // Held until the sidecar is registered, so it can't be spawned after the launch was stopped
+        let lifecycle = state.lock_lifecycle().await;
+        // The launch was stopped or superseded while waiting for the lock
+        if state.state() != ServerState::Starting || state.server_pid().is_some() {
+            return;
         }
+        let settings = app.state::<SettingsStore>().get();
+        let profile = settings.active().clone();
 
-        sidecar_command = sidecar_command.args(["-desktop-sidecar", "true"]);
+        // Deal with a server left running by a previous session before spawning a new one
+        if let OrphanOutcome::Adopted = orphan::handle_orphan(&app, OrphanPolicy::from_env()).await
+        {
+            match lock_data_dir(&app, &profile, state.port()) {
+                Ok(lock) => state.set_data_dir_lock(lock),
+                Err(e) => log_error!(
+                    "Failed to lock the data directory of the adopted server: {}",
+                    e
+                ),
+            }
+            spawn_readiness_probe(app.clone(), state.adopted_pid());
+            return;
+        }
 
+        log_info!("Launching Seanime server with profile {:?}", profile.name);
+        let sidecar_command = match sidecar_command(&app, &profile, settings.env) {
+            Ok(command) => command,
+            Err(e) => {
+                fail_launch(
+                    &app,
+                    e.to_string(),
+                    CrashDiagnosis::new(CrashCategory::MissingBinary),
+                );
+                return;
+            }
+        };
 
-        let (mut rx, child) = match sidecar_command.spawn() {
-            Ok(result) => result,
+        // Pick the port before spawning so a port held by another process doesn't crash the server
+        let port = match port::select_port(&port::PortConfig::from_env()) {
+            Ok(port) => port,
             Err(e) => {
-                // Seanime server failed to open -> close splashscreen and display crash screen
-                if let Some(splashscreen) = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL) {
-                    splashscreen.close().unwrap();
-                }
-                if let Some(crash_screen) = app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL) {
-                    crash_screen.show().unwrap();
-                }
-                app.emit(
-                    "crash",
-                    format!("The server failed to start: {}. Closing in 10 seconds.", e),
-                )
-                    .expect("failed to emit event");
-                sleep(Duration::from_secs(10)).await;
-                std::process::exit(1);
+                let category = match e {
+                    port::PortError::Busy(_) => CrashCategory::PortInUse,
+                    port::PortError::Io(_) => CrashCategory::Unknown,
+                };
+                fail_launch(&app, e.to_string(), CrashDiagnosis::new(category));
+                return;
             }
         };
+        log_info!("Seanime server will listen on port {}", port);
+        state.set_port(port);
+
+        // Make sure no other server uses the same database
+        match lock_data_dir(&app, &profile, port) {
+            Ok(lock) => state.set_data_dir_lock(lock),
+            Err(LockError::Held(owner)) => {
+                show_data_dir_conflict(&app, &profile, owner);
+                return;
+            }
+            Err(e) => log_error!("{}, starting the server anyway", e),
+        }
+
+        // Output logged before this launch isn't considered when diagnosing a crash
+        let launched_at = chrono::Utc::now().timestamp_millis();
+        app.state::<StartupTracker>().begin(&app);
+        let (mut rx, child) =
+            match sidecar::spawn(sidecar_command.env("SEANIME_SERVER_PORT", port.to_string())) {
+                Ok(spawned) => spawned,
+                Err(e) => {
+                    state.release_data_dir_lock();
+                    fail_launch(&app, e.to_string(), diagnosis::classify_spawn_error(&e));
+                    return;
+                }
+            };
 
         // Store the child process
-        *child_process.lock().unwrap() = Some(child);
+        let pid = child.pid();
+        if let Err(child) = state.set_child(child) {
+            log_error!("A server process is already running, stopping the new one");
+            if let Err(e) = child.kill() {
+                log_error!("Failed to kill server process: {}", e);
+            }
+            return;
+        }
+        // Recorded so the next session can clean it up if this one doesn't
+        app.state::<RuntimeFile>().write(pid, port, &profile.name);
+        drop(lifecycle);
 
-        // let mut server_started = false;
+        spawn_readiness_probe(app.clone(), Some(pid));
 
         // Read server terminal output
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(line) => {
-                    let line_without_colors = strip_ansi_escapes::strip(line);
-                    match String::from_utf8(line_without_colors) {
-                        Ok(line_str) => {
-                            if !server_started.lock().unwrap().clone() {
-                                if line_str.contains("Client connected") {
-                                    sleep(Duration::from_secs(2)).await;
-
-                                    *server_started.lock().unwrap() = true;
-                                    if let Some(splashscreen) = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL) {
-                                        splashscreen.close().unwrap();
-                                    }
-                                    main_window.maximize().unwrap();
-                                    main_window.show().unwrap();
-                                }
-                            }
-                            // Emit the line to the main window
-                            main_window
-                                .emit("message", Some(format!("{}", line_str)))
-                                .expect("failed to emit event");
-
-                            println!("{}", line_str);
-                        }
-                        Err(_) => {}
-                    }
+                    handle_output(&app, OutputStream::Stdout, output::decode_line(line));
+                }
+                CommandEvent::Stderr(line) => {
+                    handle_output(&app, OutputStream::Stderr, output::decode_line(line));
+                }
+                CommandEvent::Error(e) => {
+                    handle_output(
+                        &app,
+                        OutputStream::Stderr,
+                        format!("Failed to read server output: {}", e),
+                    );
                 }
                 CommandEvent::Terminated(status) => {
-                    eprintln!(
+                    app.state::<RuntimeFile>().clear_if(pid);
+                    log_error!(
                         "Seanime server process terminated with status: {:?} {:?}",
-                        status, server_started.lock().unwrap()
+                        status,
+                        state.state()
                     );
-                    *is_shutdown.lock().unwrap() = true;
-                    // Only terminate the app if the desktop app hadn't launched
-                    if !server_started.lock().unwrap().clone() {
-                        if let Some(splashscreen) = app.get_webview_window(SPLASHSCREEN_WINDOW_LABEL) {
-                            splashscreen.close().unwrap();
-                        }
-                        #[cfg(debug_assertions)]
-                        {
-                            main_window.close_devtools();
-                        }
-                        main_window.close().unwrap();
-                        if let Some(crash_screen) = app.get_webview_window(CRASH_SCREEN_WINDOW_LABEL) {
-                            crash_screen.show().unwrap();
-                        }
 
-                        app.emit("crash", format!("Seanime server process terminated with status: {}. Closing in 10 seconds.", status.code.unwrap_or(1))).expect("failed to emit event");
+                    // The child is still registered only if it wasn't stopped on purpose,
+                    // in which case whoever stopped it is responsible for the state
+                    if state.take_child_if(pid).is_none() {
+                        break;
+                    }
+                    state.release_data_dir_lock();
 
-                        sleep(Duration::from_secs(10)).await;
-                        app.exit(1);
+                    let diagnosis = diagnose_exit(&app, status.code, status.signal, launched_at);
+                    log_error!("Seanime server crash diagnosis: {:?}", diagnosis.category);
+
+                    // Restart a server that crashed after startup while the retry budget allows it.
+                    // One that never started isn't restarted, the crash screen lets the user retry.
+                    let attempt = if state.has_started() {
+                        state.with_supervisor(|s| s.on_crash(status.code, status.signal))
+                    } else {
+                        None
+                    };
+                    let exit = diagnosis::describe_exit(status.code, status.signal);
+                    let message = if state.has_started() && attempt.is_none() {
+                        log_error!("Seanime server crashed too many times, giving up");
+                        format!(
+                            "{} Seanime server crashed repeatedly (last exit: {}).",
+                            diagnosis.explanation, exit
+                        )
+                    } else {
+                        format!(
+                            "{} The server process terminated with {}.",
+                            diagnosis.explanation, exit
+                        )
+                    };
+                    let details = CrashDetails {
+                        diagnosis: Some(diagnosis),
+                        will_restart: attempt.is_some(),
+                        ..Default::default()
+                    };
+                    let _ = state.transition_with_details(
+                        &app,
+                        ServerState::Crashed,
+                        Some(message),
+                        details,
+                    );
+
+                    if let Some(attempt) = attempt {
+                        log_error!(
+                            "Restarting Seanime server in {}ms (attempt {}/{})",
+                            attempt.delay_ms,
+                            attempt.attempt,
+                            attempt.max_retries
+                        );
+                        if let Err(e) = app.emit("server-restart", attempt.clone()) {
+                            log_error!("Failed to emit server-restart event: {}", e);
+                        }
+                        let message = format!(
+                            "Restart attempt {}/{}",
+                            attempt.attempt, attempt.max_retries
+                        );
+                        let delay = Duration::from_millis(attempt.delay_ms);
+                        if let Err(e) = restart_after_crash(&app, delay, message).await {
+                            log_error!("Failed to restart the server: {}", e);
+                        }
                     }
                     break;
                 }
@@ -113,4 +222,452 @@ pub fn launch_seanime_server(
             }
         }
     });
+
+    Ok(())
+}
+
+/// Builds the command running the sidecar with `profile`.
+fn sidecar_command(
+    app: &AppHandle,
+    profile: &Profile,
+    env: BTreeMap<String, String>,
+) -> Result<Command, DesktopError> {
+    let mut sidecar_command = app.shell().sidecar("seanime")?;
+
+    match &profile.data_dir {
+        Some(data_dir) => {
+            sidecar_command = sidecar_command.args(["-datadir", data_dir.as_str()]);
+        }
+        None => {
+            // Use test data dir during development
+            #[cfg(dev)]
+            {
+                sidecar_command = sidecar_command.args(["-datadir", env!("TEST_DATADIR")]);
+            }
+        }
+    }
+
+    Ok(sidecar_command
+        .args(["-desktop-sidecar", "true"])
+        .args(&profile.extra_args)
+        .envs(env)
+        .env(shutdown::SHUTDOWN_TOKEN_ENV, shutdown::session_token()))
+}
+
+/// Prints and logs a line of server output and forwards it to the webview as a structured record.
+fn handle_output(app: &AppHandle, stream: OutputStream, line: String) {
+    let parsed = zerolog::parse_line(&line);
+    let state = app.state::<SidecarState>();
+
+    logging::write_sidecar(stream, &line);
+    app.state::<LogBuffer>().push(stream, &line, parsed.level);
+    state.record_log(stream, &parsed);
+    app.state::<StartupTracker>().on_log(app, &parsed);
+    // Keep the last stderr lines around for the crash screen
+    if stream == OutputStream::Stderr {
+        state.push_stderr(line.clone());
+    }
+
+    let payload = ServerLogEvent {
+        stream,
+        log: parsed,
+        line,
+    };
+    if let Err(e) = app.emit(SERVER_LOG_EVENT, payload) {
+        log_error!("Failed to emit server-log event: {}", e);
+    }
+}
+
+/// Diagnoses the exit of the sidecar launched at `launched_at` from its exit status and output.
+fn diagnose_exit(
+    app: &AppHandle,
+    code: Option<i32>,
+    signal: Option<i32>,
+    launched_at: i64,
+) -> CrashDiagnosis {
+    let lines: Vec<String> = app
+        .state::<LogBuffer>()
+        .query(&LogFilter {
+            since: Some(launched_at),
+            limit: Some(CRASH_DIAGNOSIS_LOG_LINES),
+            ..Default::default()
+        })
+        .into_iter()
+        .map(|entry| entry.line)
+        .collect();
+    let last_fatal = app.state::<SidecarState>().log_stats().last_fatal;
+    diagnosis::classify(code, signal, last_fatal.as_deref(), &lines)
+}
+
+/// Shows the crash screen for a server that couldn't be spawned.
+fn fail_launch(app: &AppHandle, error: String, diagnosis: CrashDiagnosis) {
+    let message = format!("The server failed to start: {}.", error);
+    let details = CrashDetails {
+        diagnosis: Some(diagnosis),
+        ..Default::default()
+    };
+    let _ = app.state::<SidecarState>().transition_with_details(
+        app,
+        ServerState::Crashed,
+        Some(message),
+        details,
+    );
+}
+
+/// Locks the data directory of `profile` on behalf of the sidecar listening on `port`.
+fn lock_data_dir(app: &AppHandle, profile: &Profile, port: u16) -> Result<DataDirLock, LockError> {
+    let data_dir = data_dir_lock::data_dir_of(app, profile).ok_or_else(|| {
+        LockError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "the data directory could not be determined",
+        ))
+    })?;
+    DataDirLock::acquire(&data_dir, &LockOwner::desktop(port, &profile.name))
+}
+
+/// Shows the crash screen when another process uses the data directory of `profile`,
+/// offering to connect to its server or to launch another profile.
+fn show_data_dir_conflict(app: &AppHandle, profile: &Profile, owner: Option<LockOwner>) {
+    let message = format!(
+        "Seanime can't start because {}.",
+        LockError::Held(owner.clone())
+    );
+    log_error!("{}", message);
+    let conflict = DataDirConflict {
+        data_dir: data_dir_lock::data_dir_of(app, profile)
+            .map(|dir| dir.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        server_url: owner.as_ref().map(LockOwner::server_url),
+        owner,
+        profiles: profiles::profiles_of(app)
+            .profiles
+            .into_iter()
+            .filter(|name| *name != profile.name)
+            .collect(),
+    };
+    let details = CrashDetails {
+        diagnosis: Some(CrashDiagnosis::new(CrashCategory::DataDirInUse)),
+        conflict: Some(conflict),
+        ..Default::default()
+    };
+    let _ = app.state::<SidecarState>().transition_with_details(
+        app,
+        ServerState::Crashed,
+        Some(message),
+        details,
+    );
+}
+
+/// Waits for the server to answer on its status endpoint before marking it as ready.
+/// `pid` identifies the sidecar process being started or adopted, None for a remote server.
+fn spawn_readiness_probe(app: AppHandle, pid: Option<u32>) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<SidecarState>();
+        let base_url = state.base_url();
+        let config = health::ReadinessConfig::from_env();
+        // Stop probing if this process is no longer the one being started
+        let is_stale = || state.state() != ServerState::Starting || state.server_pid() != pid;
+        match health::wait_until_ready(&base_url, &config, is_stale).await {
+            Ok(elapsed) => {
+                log_info!("Seanime server ready after {:?}", elapsed);
+                // The probe may answer before the listening message is read
+                app.state::<StartupTracker>()
+                    .reach(&app, StartupStage::HttpListening);
+                if let Err(e) = send_base_url(&app, &base_url) {
+                    log_error!("Failed to send the server URL to the main window: {}", e);
+                }
+                // Switches from the splashscreen or the crash screen to the main window, see [screens]
+                if state.transition(&app, ServerState::Ready, None).is_ok() {
+                    state.mark_started();
+                    watchdog::spawn(app.clone(), pid);
+                }
+            }
+            Err(health::ReadinessError::Aborted) => {}
+            Err(health::ReadinessError::TimedOut(timeout)) => {
+                handle_startup_timeout(&app, pid, timeout).await;
+            }
+            Err(e @ health::ReadinessError::Client(_)) => {
+                log_error!("Failed to probe the Seanime server: {}", e);
+                let _lifecycle = state.lock_lifecycle().await;
+                if is_stale() {
+                    return;
+                }
+                // Without a probe the server would never be marked as ready
+                if let Some(pid) = pid {
+                    stop_sidecar_process(&app, pid).await;
+                    state.release_data_dir_lock();
+                }
+                fail_launch(
+                    &app,
+                    e.to_string(),
+                    CrashDiagnosis::new(CrashCategory::Unknown),
+                );
+            }
+        }
+    });
+}
+
+/// Points the web app in the main window at the server's URL.
+fn send_base_url(app: &AppHandle, base_url: &str) -> Result<(), DesktopError> {
+    let script = format!(
+        "window.__SEANIME_SERVER_BASE_URL__ = {}",
+        serde_json::to_string(base_url)?
+    );
+    screens::main_window(app)?.eval(&script)?;
+    Ok(())
+}
+
+/// Sends the server URL again when the main window loads a page, a reload clears it.
+/// Before the server is ready, the readiness probe sends it instead.
+pub fn on_page_load(webview: &Webview, payload: &PageLoadPayload<'_>) {
+    if webview.label() != MAIN_WINDOW_LABEL || payload.event() != PageLoadEvent::Finished {
+        return;
+    }
+    let app = webview.app_handle();
+    let Some(state) = app.try_state::<SidecarState>() else {
+        return;
+    };
+    if state.state() != ServerState::Ready {
+        return;
+    }
+    if let Err(e) = send_base_url(app, &state.base_url()) {
+        log_error!("Failed to send the server URL to the main window: {}", e);
+    }
+}
+
+/// Kills a server that didn't become ready before the startup deadline, which brings up the crash screen.
+/// The crash screen offers to retry, which goes through [restart_seanime_server].
+async fn handle_startup_timeout(app: &AppHandle, pid: Option<u32>, timeout: Duration) {
+    let state = app.state::<SidecarState>();
+    let _lifecycle = state.lock_lifecycle().await;
+    // The server was stopped or restarted while the probe was running
+    if state.state() != ServerState::Starting {
+        return;
+    }
+
+    let message = match pid {
+        Some(pid) => {
+            if state.server_pid() != Some(pid) {
+                return;
+            }
+            log_error!(
+                "Seanime server did not start within {} seconds, killing it",
+                timeout.as_secs()
+            );
+            stop_sidecar_process(app, pid).await;
+            state.release_data_dir_lock();
+            format!(
+                "The server did not start within {} seconds.",
+                timeout.as_secs()
+            )
+        }
+        None => {
+            let message = format!(
+                "The server at {} did not respond within {} seconds.",
+                state.base_url(),
+                timeout.as_secs()
+            );
+            log_error!("{}", message);
+            message
+        }
+    };
+
+    let logs: Vec<String> = app
+        .state::<LogBuffer>()
+        .query(&LogFilter {
+            limit: Some(STARTUP_TIMEOUT_LOG_LINES),
+            ..Default::default()
+        })
+        .into_iter()
+        .map(|entry| entry.line)
+        .collect();
+    let details = CrashDetails {
+        diagnosis: Some(diagnosis::classify_timeout(&logs, pid.is_none())),
+        logs,
+        ..Default::default()
+    };
+    let _ = state.transition_with_details(app, ServerState::Crashed, Some(message), details);
+}
+
+/// Launches the server if it isn't running.
+pub async fn start_seanime_server(app: &AppHandle) -> Result<(), InvalidTransition> {
+    let state = app.state::<SidecarState>();
+    let _lifecycle = state.lock_lifecycle().await;
+    launch_seanime_server(app.clone())
+}
+
+/// Stops the running server, if any, and waits for it to exit.
+pub async fn stop_seanime_server(app: &AppHandle) -> Result<(), InvalidTransition> {
+    let state = app.state::<SidecarState>();
+    let _lifecycle = state.lock_lifecycle().await;
+    if state.state() == ServerState::Stopped {
+        return Ok(());
+    }
+    state.transition(app, ServerState::Stopping, None)?;
+    stop_running_sidecar(app).await;
+    state.transition(app, ServerState::Stopped, None)?;
+    Ok(())
+}
+
+/// Stops the sidecar of this session, spawned or adopted, and removes its runtime record.
+async fn stop_running_sidecar(app: &AppHandle) {
+    let state = app.state::<SidecarState>();
+    for pid in [state.child_pid(), state.adopted_pid()]
+        .into_iter()
+        .flatten()
+    {
+        stop_sidecar_process(app, pid).await;
+    }
+    state.release_data_dir_lock();
+}
+
+/// Stops the sidecar identified by `pid`, spawned or adopted, and removes its runtime record.
+/// Returns false if it isn't the sidecar of this session.
+async fn stop_sidecar_process(app: &AppHandle, pid: u32) -> bool {
+    let state = app.state::<SidecarState>();
+    let runtime = app.state::<RuntimeFile>();
+    let base_url = state.base_url();
+    if let Some(child) = state.take_child_if(pid) {
+        shutdown::stop_server(
+            child,
+            &base_url,
+            shutdown::session_token(),
+            shutdown::drain_timeout(),
+        )
+        .await;
+    } else if state.adopted_pid() == Some(pid) {
+        state.take_adopted();
+        // The adopted server was spawned by a previous session, with its own token
+        let token = runtime
+            .read()
+            .filter(|record| record.pid == pid)
+            .map(|record| record.shutdown_token)
+            .unwrap_or_default();
+        shutdown::terminate_pid(pid, &base_url, &token, shutdown::drain_timeout()).await;
+    } else {
+        return false;
+    }
+    runtime.clear_if(pid);
+    true
+}
+
+/// Stops the running server, if any, and launches a new one.
+/// Joins the restart already in progress instead of starting another one.
+pub async fn restart_seanime_server(app: &AppHandle) -> Result<(), InvalidTransition> {
+    join_or_run_restart(app, || async {
+        let state = app.state::<SidecarState>();
+        let _lifecycle = state.lock_lifecycle().await;
+        if state.state() != ServerState::Stopped {
+            state.transition(app, ServerState::Restarting, None)?;
+            log_info!("Stopping existing server process");
+            stop_running_sidecar(app).await;
+        }
+        // A restart requested by the user starts with a fresh retry budget
+        state.with_supervisor(|s| s.reset());
+        launch_seanime_server(app.clone())
+    })
+    .await
+}
+
+/// Kills a server that stopped answering health checks and relaunches it,
+/// counting against the same retry budget as crashes. The crash screen is shown once the budget is spent.
+pub async fn restart_unhealthy_server(app: &AppHandle, pid: u32) {
+    let state = app.state::<SidecarState>();
+    let attempt = {
+        let _lifecycle = state.lock_lifecycle().await;
+        // The server recovered or was stopped or restarted in the meantime
+        if state.state() != ServerState::Unhealthy {
+            return;
+        }
+        if state.server_pid() != Some(pid) {
+            return;
+        }
+        let attempt = state.with_supervisor(|s| s.on_crash(None, None));
+        log_info!("Killing unresponsive Seanime server");
+        stop_sidecar_process(app, pid).await;
+        state.release_data_dir_lock();
+
+        let Some(attempt) = attempt else {
+            log_error!("Seanime server stopped responding too many times, not restarting it");
+            let diagnosis = CrashDiagnosis::new(CrashCategory::Unresponsive);
+            let message = diagnosis.explanation.clone();
+            let details = CrashDetails {
+                diagnosis: Some(diagnosis),
+                ..Default::default()
+            };
+            let _ =
+                state.transition_with_details(app, ServerState::Crashed, Some(message), details);
+            return;
+        };
+        attempt
+    };
+
+    log_error!(
+        "Restarting Seanime server in {}ms (attempt {}/{})",
+        attempt.delay_ms,
+        attempt.attempt,
+        attempt.max_retries
+    );
+    if let Err(e) = app.emit("server-restart", attempt.clone()) {
+        log_error!("Failed to emit server-restart event: {}", e);
+    }
+    let message = format!(
+        "Restart attempt {}/{}",
+        attempt.attempt, attempt.max_retries
+    );
+    let delay = Duration::from_millis(attempt.delay_ms);
+    if let Err(e) = restart_after_crash(app, delay, message).await {
+        log_error!("Failed to restart the server: {}", e);
+    }
+}
+
+/// Relaunches a crashed or unresponsive server after the supervisor's backoff delay.
+async fn restart_after_crash(
+    app: &AppHandle,
+    delay: Duration,
+    message: String,
+) -> Result<(), InvalidTransition> {
+    join_or_run_restart(app, || async {
+        let state = app.state::<SidecarState>();
+        {
+            let _lifecycle = state.lock_lifecycle().await;
+            // The server was stopped or restarted since it went down
+            if !matches!(state.state(), ServerState::Crashed | ServerState::Unhealthy) {
+                return Ok(());
+            }
+            state.transition(app, ServerState::Restarting, Some(message))?;
+        }
+        sleep(delay).await;
+
+        let _lifecycle = state.lock_lifecycle().await;
+        // The server was stopped or relaunched during the backoff
+        if state.state() != ServerState::Restarting || state.server_pid().is_some() {
+            return Ok(());
+        }
+        launch_seanime_server(app.clone())
+    })
+    .await
+}
+
+async fn join_or_run_restart<F, Fut>(app: &AppHandle, restart: F) -> RestartResult
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = RestartResult>,
+{
+    let state = app.state::<SidecarState>();
+    match state.begin_restart() {
+        RestartTicket::Follower(mut rx) => {
+            log_info!("Joining the server restart already in progress");
+            match rx.wait_for(|result| result.is_some()).await {
+                Ok(result) => result.clone().unwrap_or(Ok(())),
+                // The restart was dropped before finishing
+                Err(_) => Ok(()),
+            }
+        }
+        RestartTicket::Leader(tx) => {
+            let result = restart().await;
+            state.finish_restart(tx, result.clone());
+            result
+        }
+    }
 }
@@ -0,0 +1,541 @@
+use crate::error::LockExt;
+use crate::logging::{log_error, log_info};
+use crate::mode::{self, ServerMode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const SETTINGS_FILE_NAME: &str = "desktop-settings.json";
+/// Version written to the settings file, bumped whenever a migration is added
+pub const SETTINGS_VERSION: u32 = 2;
+pub const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// Arguments and environment variables the desktop app sets itself
+const MANAGED_ARGS: &[&str] = &["-datadir", "-desktop-sidecar"];
+const MANAGED_ENV: &[&str] = &["SEANIME_SERVER_PORT", "SEANIME_DATA_DIR"];
+/// Environment variables that make the dynamic loader inject code into the sidecar, matched as prefixes
+const DENIED_ENV_PREFIXES: &[&str] = &["LD_", "DYLD_", "GCONV_PATH"];
+
+/// A named data directory with its own sidecar arguments.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    /// Data directory passed to the sidecar, the server's default is used if None
+    #[serde(default)]
+    pub data_dir: Option<String>,
+    /// Additional arguments passed to the sidecar
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            data_dir: None,
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+/// Settings of the desktop app, stored in the app config dir.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DesktopSettings {
+    pub version: u32,
+    pub profiles: Vec<Profile>,
+    /// Name of the profile the sidecar is launched with, the last one used
+    pub active_profile: String,
+    /// Environment variables set for the sidecar
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub server_mode: ServerMode,
+}
+
+impl Default for DesktopSettings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            profiles: vec![Profile::new(DEFAULT_PROFILE_NAME)],
+            active_profile: DEFAULT_PROFILE_NAME.to_string(),
+            env: BTreeMap::new(),
+            server_mode: ServerMode::Sidecar,
+        }
+    }
+}
+
+impl DesktopSettings {
+    /// Checks the settings, returning every problem found.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let mut problems = Vec::new();
+
+        if self.profiles.is_empty() {
+            problems.push("at least one profile is required".to_string());
+        }
+        if self.profile(&self.active_profile).is_none() {
+            problems.push(format!(
+                "active profile {:?} does not exist",
+                self.active_profile
+            ));
+        }
+
+        for (i, profile) in self.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                problems.push("profile name is empty".to_string());
+            }
+            // Profiles sharing a data directory would share their libraries and accounts
+            for other in &self.profiles[..i] {
+                if other.name == profile.name {
+                    problems.push(format!(
+                        "profile {:?} is defined more than once",
+                        profile.name
+                    ));
+                } else if other.data_dir == profile.data_dir {
+                    problems.push(format!(
+                        "profiles {:?} and {:?} use the same data directory",
+                        other.name, profile.name
+                    ));
+                }
+            }
+
+            if let Some(data_dir) = &profile.data_dir {
+                if data_dir.trim().is_empty() {
+                    problems.push(format!(
+                        "data directory of profile {:?} is empty",
+                        profile.name
+                    ));
+                } else if !Path::new(data_dir).is_absolute() {
+                    problems.push(format!(
+                        "data directory {:?} of profile {:?} is not an absolute path",
+                        data_dir, profile.name
+                    ));
+                }
+            }
+
+            for arg in &profile.extra_args {
+                let name = arg.split('=').next().unwrap_or(arg);
+                if MANAGED_ARGS.contains(&name) {
+                    problems.push(format!("argument {:?} is set by the desktop app", name));
+                }
+            }
+        }
+
+        for key in self.env.keys() {
+            if key.is_empty() || key.contains('=') || key.contains('\0') {
+                problems.push(format!("invalid environment variable name {:?}", key));
+            } else if MANAGED_ENV.contains(&key.as_str()) {
+                problems.push(format!(
+                    "environment variable {} is set by the desktop app",
+                    key
+                ));
+            } else if is_denied_env(key) {
+                problems.push(format!("environment variable {} can't be set", key));
+            }
+        }
+
+        if let ServerMode::Remote(url) = &self.server_mode {
+            if let Err(e) = mode::parse_server_url(url) {
+                problems.push(format!("invalid server URL {:?}: {}", url, e));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(SettingsError::Invalid(problems))
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Profile the sidecar is launched with. Falls back to the first one.
+    pub fn active(&self) -> &Profile {
+        self.profile(&self.active_profile)
+            .unwrap_or(&self.profiles[0])
+    }
+
+    /// Whether going from `self` to `other` requires restarting the server.
+    pub fn requires_restart(&self, other: &DesktopSettings) -> bool {
+        self.active() != other.active()
+            || self.env != other.env
+            || self.server_mode != other.server_mode
+    }
+}
+
+/// Whether the environment variable can load code into the sidecar, e.g. LD_PRELOAD or DYLD_INSERT_LIBRARIES.
+fn is_denied_env(key: &str) -> bool {
+    let key = key.to_uppercase();
+    DENIED_ENV_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// The file was written by a newer version of the app
+    UnsupportedVersion(u32),
+    /// The file has no version, it wasn't written by the app
+    Unversioned,
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "could not access the settings file: {}", e),
+            SettingsError::Parse(e) => write!(f, "could not parse the settings file: {}", e),
+            SettingsError::UnsupportedVersion(v) => write!(
+                f,
+                "settings version {} is newer than the supported version {}",
+                v, SETTINGS_VERSION
+            ),
+            SettingsError::Unversioned => write!(f, "the settings file has no version"),
+            SettingsError::Invalid(problems) => {
+                write!(f, "invalid settings: {}", problems.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SettingsError {
+    fn from(e: serde_json::Error) -> Self {
+        SettingsError::Parse(e)
+    }
+}
+
+/// Brings a settings file written by an older version up to [SETTINGS_VERSION].
+fn migrate(mut value: Value) -> Result<Value, SettingsError> {
+    let Some(version) = value.get("version").and_then(Value::as_u64) else {
+        return Err(SettingsError::Unversioned);
+    };
+    let mut version = version as u32;
+    if version > SETTINGS_VERSION {
+        return Err(SettingsError::UnsupportedVersion(version));
+    }
+
+    while version < SETTINGS_VERSION {
+        let Some(object) = value.as_object_mut() else {
+            break;
+        };
+        // The data dir and sidecar arguments moved into profiles
+        if version == 1 {
+            let mut profile = serde_json::Map::new();
+            profile.insert("name".to_string(), Value::from(DEFAULT_PROFILE_NAME));
+            for key in ["dataDir", "extraArgs"] {
+                if let Some(field) = object.remove(key) {
+                    profile.insert(key.to_string(), field);
+                }
+            }
+            object.insert(
+                "profiles".to_string(),
+                Value::Array(vec![Value::Object(profile)]),
+            );
+            object.insert(
+                "activeProfile".to_string(),
+                Value::from(DEFAULT_PROFILE_NAME),
+            );
+        }
+        version += 1;
+    }
+
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(SETTINGS_VERSION));
+    }
+    Ok(value)
+}
+
+/// Reads, migrates and validates the settings file. A missing file yields the defaults.
+pub fn load(path: &Path) -> Result<DesktopSettings, SettingsError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(DesktopSettings::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let value = migrate(serde_json::from_str(&content)?)?;
+    let settings: DesktopSettings = serde_json::from_value(value)?;
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Writes the settings file, going through a temporary file so it is never left half-written.
+pub fn save(path: &Path, settings: &DesktopSettings) -> Result<(), SettingsError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(settings)?)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Copies a settings file that can't be used next to it, so saving the defaults doesn't lose it.
+fn backup(path: &Path) {
+    if !path.exists() {
+        return;
+    }
+    let backup_path = path.with_extension("json.bak");
+    match fs::copy(path, &backup_path) {
+        Ok(_) => log_info!(
+            "Backed up the desktop settings to {}",
+            backup_path.display()
+        ),
+        Err(e) => log_error!("Failed to back up the desktop settings: {}", e),
+    }
+}
+
+/// Desktop settings and the file they're stored in, registered with `app.manage`.
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Mutex<DesktopSettings>,
+}
+
+impl SettingsStore {
+    /// Loads the settings from `path`, falling back to the defaults if the file can't be used.
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log_error!("Failed to load desktop settings, using defaults: {}", e);
+                backup(&path);
+                DesktopSettings::default()
+            }
+        };
+        log_info!("Desktop settings loaded from {}", path.display());
+        Self {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> DesktopSettings {
+        self.settings.lock_or_recover().clone()
+    }
+
+    /// Validates and saves new settings. Returns the previous settings.
+    pub fn set(&self, mut settings: DesktopSettings) -> Result<DesktopSettings, SettingsError> {
+        settings.version = SETTINGS_VERSION;
+        settings.validate()?;
+        let mut current = self.settings.lock_or_recover();
+        save(&self.path, &settings)?;
+        Ok(std::mem::replace(&mut *current, settings))
+    }
+
+    /// Makes `name` the active profile and saves it. Returns whether it changed.
+    pub fn set_active_profile(&self, name: &str) -> Result<bool, SettingsError> {
+        let mut current = self.settings.lock_or_recover();
+        if current.profile(name).is_none() {
+            return Err(SettingsError::Invalid(vec![format!(
+                "profile {:?} does not exist",
+                name
+            )]));
+        }
+        if current.active_profile == name {
+            return Ok(false);
+        }
+        let mut settings = current.clone();
+        settings.active_profile = name.to_string();
+        save(&self.path, &settings)?;
+        *current = settings;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Name of the case, change making the settings invalid and expected problem.
+    type InvalidCase = (&'static str, fn(&mut DesktopSettings), &'static str);
+
+    fn settings() -> DesktopSettings {
+        let mut settings = DesktopSettings::default();
+        settings.profiles.push(Profile {
+            name: "Work".to_string(),
+            data_dir: Some(absolute("work")),
+            extra_args: vec!["-port=4000".to_string()],
+        });
+        settings
+    }
+
+    fn absolute(name: &str) -> String {
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Problems reported by [DesktopSettings::validate], none if the settings are valid.
+    fn problems(settings: &DesktopSettings) -> Vec<String> {
+        match settings.validate() {
+            Ok(()) => Vec::new(),
+            Err(SettingsError::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let data_dir = absolute("seanime");
+        let value = migrate(json!({
+            "version": 1,
+            "dataDir": data_dir,
+            "extraArgs": ["-port=4000"],
+            "env": { "SEANIME_DEBUG": "true" },
+        }))
+        .unwrap();
+        let settings: DesktopSettings = serde_json::from_value(value).unwrap();
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.active_profile, DEFAULT_PROFILE_NAME);
+        assert_eq!(
+            settings.profiles,
+            vec![Profile {
+                name: DEFAULT_PROFILE_NAME.to_string(),
+                data_dir: Some(data_dir),
+                extra_args: vec!["-port=4000".to_string()],
+            }]
+        );
+        assert_eq!(
+            settings.env.get("SEANIME_DEBUG").map(String::as_str),
+            Some("true")
+        );
+        settings.validate().unwrap();
+    }
+
+    #[test]
+    fn keeps_current_settings() {
+        let value = serde_json::to_value(settings()).unwrap();
+        assert_eq!(migrate(value.clone()).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_unversioned_settings() {
+        let result = migrate(json!({ "dataDir": absolute("seanime"), "env": {} }));
+        assert!(matches!(result, Err(SettingsError::Unversioned)));
+    }
+
+    #[test]
+    fn rejects_newer_settings() {
+        let result = migrate(json!({ "version": SETTINGS_VERSION + 1 }));
+        assert!(
+            matches!(result, Err(SettingsError::UnsupportedVersion(v)) if v == SETTINGS_VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn accepts_valid_settings() {
+        assert!(problems(&DesktopSettings::default()).is_empty());
+        assert!(problems(&settings()).is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_settings() {
+        let cases: Vec<InvalidCase> = vec![
+            (
+                "no profiles",
+                |s| s.profiles.clear(),
+                "at least one profile",
+            ),
+            (
+                "missing active profile",
+                |s| s.active_profile = "Other".to_string(),
+                "active profile \"Other\" does not exist",
+            ),
+            (
+                "empty name",
+                |s| s.profiles[1].name = " ".to_string(),
+                "profile name is empty",
+            ),
+            (
+                "duplicate name",
+                |s| s.profiles[1].name = DEFAULT_PROFILE_NAME.to_string(),
+                "is defined more than once",
+            ),
+            (
+                "shared data dir",
+                |s| s.profiles[1].data_dir = None,
+                "use the same data directory",
+            ),
+            (
+                "relative data dir",
+                |s| s.profiles[1].data_dir = Some("seanime".to_string()),
+                "is not an absolute path",
+            ),
+            (
+                "managed argument",
+                |s| s.profiles[1].extra_args.push("-datadir=/tmp".to_string()),
+                "argument \"-datadir\" is set by the desktop app",
+            ),
+            (
+                "invalid env name",
+                |s| {
+                    s.env.insert("A=B".to_string(), String::new());
+                },
+                "invalid environment variable name",
+            ),
+            (
+                "managed env",
+                |s| {
+                    s.env
+                        .insert("SEANIME_SERVER_PORT".to_string(), "4000".to_string());
+                },
+                "SEANIME_SERVER_PORT is set by the desktop app",
+            ),
+            (
+                "loader env",
+                |s| {
+                    s.env
+                        .insert("LD_PRELOAD".to_string(), "/tmp/inject.so".to_string());
+                },
+                "LD_PRELOAD can't be set",
+            ),
+            (
+                "macOS loader env",
+                |s| {
+                    s.env.insert(
+                        "dyld_insert_libraries".to_string(),
+                        "/tmp/inject.dylib".to_string(),
+                    );
+                },
+                "dyld_insert_libraries can't be set",
+            ),
+            (
+                "invalid server URL",
+                |s| s.server_mode = ServerMode::Remote("not a url".to_string()),
+                "invalid server URL",
+            ),
+        ];
+
+        for (name, change, expected) in cases {
+            let mut settings = settings();
+            change(&mut settings);
+            let problems = problems(&settings);
+            assert!(
+                problems.iter().any(|problem| problem.contains(expected)),
+                "{}: expected a problem containing {:?}, got {:?}",
+                name,
+                expected,
+                problems
+            );
+        }
+    }
+}
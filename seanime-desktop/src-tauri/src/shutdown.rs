@@ -0,0 +1,173 @@
+use crate::constants::{
+    SERVER_SHUTDOWN_PATH, SHUTDOWN_DRAIN_TIMEOUT_SECS, SHUTDOWN_REQUEST_TIMEOUT_MS,
+};
+use crate::logging::{log_error, log_info};
+use crate::sidecar::SidecarChild;
+use std::sync::OnceLock;
+use tokio::time::Duration;
+
+/// Environment variable through which the sidecar receives the shutdown token.
+pub const SHUTDOWN_TOKEN_ENV: &str = "SEANIME_DESKTOP_SHUTDOWN_TOKEN";
+/// Header the shutdown endpoint expects the token in.
+const SHUTDOWN_TOKEN_HEADER: &str = "X-Seanime-Shutdown-Token";
+/// Random bytes in the shutdown token
+const SHUTDOWN_TOKEN_BYTES: usize = 32;
+
+/// Drain timeout, overridable through `SEANIME_DESKTOP_SHUTDOWN_TIMEOUT` (seconds).
+pub fn drain_timeout() -> Duration {
+    let secs = std::env::var("SEANIME_DESKTOP_SHUTDOWN_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(SHUTDOWN_DRAIN_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Token given to the sidecars of this session, only the desktop app can make them shut down with it.
+/// Empty if the OS random generator failed, the sidecars then refuse every shutdown request.
+pub fn session_token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| {
+        let mut bytes = [0u8; SHUTDOWN_TOKEN_BYTES];
+        match getrandom::getrandom(&mut bytes) {
+            Ok(()) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            Err(e) => {
+                log_error!("Failed to generate the shutdown token: {}", e);
+                String::new()
+            }
+        }
+    })
+}
+
+/// Asks the server to exit and waits up to `drain_timeout` for it to do so,
+/// giving it a chance to close the database and stop its clients.
+/// The process is force-killed if it is still running after the timeout.
+pub async fn stop_server(
+    child: SidecarChild,
+    base_url: &str,
+    token: &str,
+    drain_timeout: Duration,
+) {
+    let pid = child.pid();
+    if request_exit(pid, base_url, token).await {
+        if wait_for_exit(pid, drain_timeout).await {
+            log_info!("Server process {} exited", pid);
+            return;
+        }
+        log_error!(
+            "Server process {} did not exit within {:?}, killing it",
+            pid,
+            drain_timeout
+        );
+    }
+
+    if let Err(e) = child.kill() {
+        log_error!("Failed to kill server process: {}", e);
+    }
+}
+
+/// Stops a process that isn't a child of this app, such as a server left running by a previous session.
+pub async fn terminate_pid(pid: u32, base_url: &str, token: &str, drain_timeout: Duration) {
+    if request_exit(pid, base_url, token).await && wait_for_exit(pid, drain_timeout).await {
+        log_info!("Server process {} exited", pid);
+        return;
+    }
+    log_error!(
+        "Server process {} did not exit within {:?}, killing it",
+        pid,
+        drain_timeout
+    );
+
+    #[cfg(unix)]
+    {
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } != 0 {
+            log_error!("Failed to kill server process {}", pid);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        let result = std::process::Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+        if let Err(e) = result {
+            log_error!("Failed to kill server process {}: {}", pid, e);
+        }
+    }
+}
+
+/// Asks the server to run its cleanup and exit, through the shutdown endpoint or, failing that, SIGTERM.
+/// Returns false if the server couldn't be asked.
+async fn request_exit(pid: u32, base_url: &str, token: &str) -> bool {
+    match request_shutdown(base_url, token).await {
+        Ok(()) => {
+            log_info!("Asked server process {} to shut down", pid);
+            return true;
+        }
+        Err(e) => log_error!("Shutdown request to server process {} failed: {}", pid, e),
+    }
+
+    #[cfg(unix)]
+    {
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) == 0 } {
+            log_info!("Sent SIGTERM to server process {}", pid);
+            return true;
+        }
+    }
+    false
+}
+
+/// Calls the shutdown endpoint of the server, which answers before it starts shutting down.
+async fn request_shutdown(base_url: &str, token: &str) -> Result<(), reqwest::Error> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(SHUTDOWN_REQUEST_TIMEOUT_MS))
+        .build()?;
+    let url = format!("{}{}", base_url.trim_end_matches('/'), SERVER_SHUTDOWN_PATH);
+    client
+        .post(url)
+        .header(SHUTDOWN_TOKEN_HEADER, token)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, WAIT_TIMEOUT};
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_SYNCHRONIZE, 0, pid);
+        // The process no longer exists
+        if handle == 0 {
+            return false;
+        }
+        let running = WaitForSingleObject(handle, 0) == WAIT_TIMEOUT;
+        CloseHandle(handle);
+        running
+    }
+}
+
+async fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    use tokio::time::{sleep, Instant};
+
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if !is_running(pid) {
+            return true;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    !is_running(pid)
+}
@@ -0,0 +1,56 @@
+#[cfg(target_os = "linux")]
+use crate::process_group::{self, GroupChild};
+use std::io;
+use tauri::async_runtime::Receiver;
+#[cfg(not(target_os = "linux"))]
+use tauri_plugin_shell::process::CommandChild;
+use tauri_plugin_shell::process::{Command, CommandEvent};
+
+/// Handle to the running sidecar process.
+pub enum SidecarChild {
+    #[cfg(not(target_os = "linux"))]
+    Shell(CommandChild),
+    /// Spawned in its own process group, see [process_group]
+    #[cfg(target_os = "linux")]
+    Group(GroupChild),
+}
+
+impl SidecarChild {
+    pub fn pid(&self) -> u32 {
+        match self {
+            #[cfg(not(target_os = "linux"))]
+            SidecarChild::Shell(child) => child.pid(),
+            #[cfg(target_os = "linux")]
+            SidecarChild::Group(child) => child.pid(),
+        }
+    }
+
+    pub fn kill(self) -> io::Result<()> {
+        match self {
+            #[cfg(not(target_os = "linux"))]
+            SidecarChild::Shell(child) => child
+                .kill()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())),
+            #[cfg(target_os = "linux")]
+            SidecarChild::Group(child) => child.kill(),
+        }
+    }
+}
+
+/// Spawns the sidecar. On Linux it is tied to the lifetime of the desktop process,
+/// along with every process it starts.
+pub fn spawn(command: Command) -> io::Result<(Receiver<CommandEvent>, SidecarChild)> {
+    #[cfg(target_os = "linux")]
+    {
+        let (rx, child) = process_group::spawn(command.into())?;
+        Ok((rx, SidecarChild::Group(child)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let (rx, child) = command
+            .spawn()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        Ok((rx, SidecarChild::Shell(child)))
+    }
+}
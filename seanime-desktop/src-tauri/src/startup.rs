@@ -0,0 +1,171 @@
+use crate::error::LockExt;
+use crate::logging::{log_error, log_info};
+use crate::zerolog::ParsedLog;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+pub const STARTUP_PROGRESS_EVENT: &str = "startup-progress";
+
+/// Steps the sidecar goes through before the app can be used, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StartupStage {
+    SpawningBinary,
+    OpeningDatabase,
+    RunningMigrations,
+    LoadingExtensions,
+    HttpListening,
+    /// The web app in the main window opened its websocket
+    WebviewConnected,
+}
+
+/// Server log messages marking the start of each stage, matched as prefixes.
+const MARKERS: &[(StartupStage, &[&str])] = &[
+    // Logged right before the database is opened
+    (StartupStage::OpeningDatabase, &["app: Data directory:"]),
+    // Logged once the schema is migrated, version migrations run next
+    (
+        StartupStage::RunningMigrations,
+        &["db: Database instantiated"],
+    ),
+    // Logged once the modules are initialized, extensions are loaded next
+    (
+        StartupStage::LoadingExtensions,
+        &["app: Refreshed modules", "app: Did not initialize modules"],
+    ),
+    (StartupStage::HttpListening, &["app: Seanime started at"]),
+    (StartupStage::WebviewConnected, &["ws: Client connected"]),
+];
+
+impl StartupStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StartupStage::SpawningBinary => "Starting the server",
+            StartupStage::OpeningDatabase => "Opening the database",
+            StartupStage::RunningMigrations => "Running migrations",
+            StartupStage::LoadingExtensions => "Loading extensions",
+            StartupStage::HttpListening => "Waiting for the server",
+            StartupStage::WebviewConnected => "Connected",
+        }
+    }
+
+    /// Stage a line of server output marks the start of, if any.
+    fn from_log(parsed: &ParsedLog) -> Option<Self> {
+        let summary = parsed.summary();
+        MARKERS
+            .iter()
+            .find(|(_, markers)| markers.iter().any(|marker| summary.starts_with(marker)))
+            .map(|(stage, _)| *stage)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageTiming {
+    pub stage: StartupStage,
+    pub label: &'static str,
+    /// Milliseconds between the spawn of the sidecar and the start of the stage
+    pub elapsed_ms: u64,
+}
+
+/// Payload of the "startup-progress" event, listing the stages reached by the current launch.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupProgress {
+    pub stages: Vec<StageTiming>,
+    /// Milliseconds the whole startup took, set once the webview is connected
+    pub total_ms: Option<u64>,
+}
+
+struct Launch {
+    started_at: Instant,
+    progress: StartupProgress,
+}
+
+/// Tracks the startup stages of the sidecar being launched.
+#[derive(Default)]
+pub struct StartupTracker {
+    launch: Mutex<Option<Launch>>,
+}
+
+impl StartupTracker {
+    /// Starts tracking a new launch, forgetting the previous one.
+    pub fn begin(&self, app: &AppHandle) {
+        *self.launch.lock_or_recover() = Some(Launch {
+            started_at: Instant::now(),
+            progress: StartupProgress::default(),
+        });
+        self.reach(app, StartupStage::SpawningBinary);
+    }
+
+    /// Records that the launch reached `stage`. Stages only move forward and nothing is recorded once it finished.
+    pub fn reach(&self, app: &AppHandle, stage: StartupStage) {
+        let progress = {
+            let mut launch = self.launch.lock_or_recover();
+            let Some(launch) = launch.as_mut() else {
+                return;
+            };
+            let progress = &mut launch.progress;
+            let is_behind = progress
+                .stages
+                .last()
+                .is_some_and(|last| stage <= last.stage);
+            if progress.total_ms.is_some() || is_behind {
+                return;
+            }
+            let elapsed_ms = launch.started_at.elapsed().as_millis() as u64;
+            progress.stages.push(StageTiming {
+                stage,
+                label: stage.label(),
+                elapsed_ms,
+            });
+            if stage == StartupStage::WebviewConnected {
+                progress.total_ms = Some(elapsed_ms);
+                log_startup_duration(progress);
+            }
+            progress.clone()
+        };
+
+        if let Err(e) = app.emit(STARTUP_PROGRESS_EVENT, progress) {
+            log_error!("Failed to emit startup-progress event: {}", e);
+        }
+    }
+
+    /// Advances the launch if the line of server output is a stage marker.
+    pub fn on_log(&self, app: &AppHandle, parsed: &ParsedLog) {
+        if let Some(stage) = StartupStage::from_log(parsed) {
+            self.reach(app, stage);
+        }
+    }
+
+    /// Stages reached by the current launch.
+    pub fn progress(&self) -> StartupProgress {
+        self.launch
+            .lock_or_recover()
+            .as_ref()
+            .map(|launch| launch.progress.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Logs the total startup duration along with the time spent in each stage, so regressions show up in the logs.
+fn log_startup_duration(progress: &StartupProgress) {
+    let stages: Vec<String> = progress
+        .stages
+        .windows(2)
+        .map(|pair| {
+            format!(
+                "{:?} {}ms",
+                pair[0].stage,
+                pair[1].elapsed_ms - pair[0].elapsed_ms
+            )
+        })
+        .collect();
+    log_info!(
+        "Seanime started in {}ms ({})",
+        progress.total_ms.unwrap_or_default(),
+        stages.join(", ")
+    );
+}
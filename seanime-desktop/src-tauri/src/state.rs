@@ -0,0 +1,513 @@
+use crate::constants::{DEFAULT_SERVER_PORT, STDERR_TAIL_LINES};
+use crate::data_dir_lock::{DataDirConflict, DataDirLock};
+use crate::diagnosis::CrashDiagnosis;
+use crate::error::LockExt;
+use crate::health;
+use crate::log_buffer::LogLevel;
+use crate::logging::{log_error, log_info};
+use crate::mode::ServerMode;
+use crate::output::OutputStream;
+use crate::sidecar::SidecarChild;
+use crate::supervisor::Supervisor;
+use crate::zerolog::ParsedLog;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+pub const SERVER_STATE_EVENT: &str = "server-state";
+
+/// Lifecycle of the sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ServerState {
+    Starting,
+    Ready,
+    /// Running but not answering health checks
+    Unhealthy,
+    Crashed,
+    Restarting,
+    Stopping,
+    Stopped,
+}
+
+impl ServerState {
+    pub fn can_transition_to(self, next: ServerState) -> bool {
+        use ServerState::*;
+        matches!(
+            (self, next),
+            (Stopped, Starting)
+                | (Starting, Ready | Crashed | Restarting | Stopping)
+                | (Ready, Unhealthy | Crashed | Restarting | Stopping)
+                | (Unhealthy, Ready | Crashed | Restarting | Stopping)
+                | (Crashed, Starting | Restarting | Stopping)
+                | (Restarting, Starting | Crashed | Stopping)
+                | (Stopping, Stopped)
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ServerState::Starting => "Starting",
+            ServerState::Ready => "Running",
+            ServerState::Unhealthy => "Not responding",
+            ServerState::Crashed => "Crashed",
+            ServerState::Restarting => "Restarting",
+            ServerState::Stopping => "Stopping",
+            ServerState::Stopped => "Stopped",
+        }
+    }
+}
+
+/// Payload of the "server-state" event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStateEvent {
+    pub state: ServerState,
+    pub previous: ServerState,
+    pub message: Option<String>,
+    /// Last lines written to stderr, sent when the server crashes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stderr: Vec<String>,
+    /// Last fatal error logged by the server, sent when the server crashes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fatal: Option<String>,
+    /// Recent server output, sent when the server fails to start in time
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    /// What went wrong and what to do about it, sent when the server crashes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnosis: Option<CrashDiagnosis>,
+    /// Set when the server can't start because another process uses its data directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<DataDirConflict>,
+    /// Whether the supervisor restarts the crashed server on its own
+    #[serde(default)]
+    pub will_restart: bool,
+}
+
+/// Details attached to a "server-state" event about a crash.
+#[derive(Debug, Clone, Default)]
+pub struct CrashDetails {
+    pub logs: Vec<String>,
+    pub diagnosis: Option<CrashDiagnosis>,
+    pub conflict: Option<DataDirConflict>,
+    pub will_restart: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidTransition {
+    pub from: ServerState,
+    pub to: ServerState,
+}
+
+impl std::fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid server state transition: {:?} -> {:?}",
+            self.from, self.to
+        )
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+pub type RestartResult = Result<(), InvalidTransition>;
+
+/// Role of a caller asking for a restart.
+pub enum RestartTicket {
+    /// No restart is in progress, the caller performs it and reports the outcome
+    Leader(watch::Sender<Option<RestartResult>>),
+    /// A restart is already in progress, the caller waits for its outcome
+    Follower(watch::Receiver<Option<RestartResult>>),
+}
+
+struct Inner {
+    state: ServerState,
+    mode: ServerMode,
+    child: Option<SidecarChild>,
+    /// Port the sidecar was told to listen on
+    port: u16,
+    /// Sidecar left running by a previous session and reused by this one
+    adopted_pid: Option<u32>,
+    /// Lock on the data directory of the running sidecar
+    data_dir_lock: Option<DataDirLock>,
+    /// Whether the server has been ready once, i.e. the main window has been shown
+    has_started: bool,
+    supervisor: Supervisor,
+    restart_in_flight: Option<watch::Receiver<Option<RestartResult>>>,
+    stderr_tail: VecDeque<String>,
+    log_stats: LogStats,
+    /// Event sent for the last crash, kept for the diagnostics summary
+    last_crash: Option<ServerStateEvent>,
+}
+
+/// Counts of the notable lines logged by the current server process.
+#[derive(Debug, Clone, Default)]
+pub struct LogStats {
+    pub warnings: u32,
+    pub errors: u32,
+    pub last_fatal: Option<String>,
+}
+
+/// Sidecar process and its lifecycle state, registered with `app.manage`.
+pub struct SidecarState {
+    inner: Mutex<Inner>,
+    /// Held for the whole duration of a launch, stop or restart so they never interleave
+    lifecycle: tokio::sync::Mutex<()>,
+}
+
+impl SidecarState {
+    pub fn new(supervisor: Supervisor, mode: ServerMode) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                state: ServerState::Stopped,
+                mode,
+                child: None,
+                port: DEFAULT_SERVER_PORT,
+                adopted_pid: None,
+                data_dir_lock: None,
+                has_started: false,
+                supervisor,
+                restart_in_flight: None,
+                stderr_tail: VecDeque::with_capacity(STDERR_TAIL_LINES),
+                log_stats: LogStats::default(),
+                last_crash: None,
+            }),
+            lifecycle: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Waits for any other lifecycle operation to finish.
+    pub async fn lock_lifecycle(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.lifecycle.lock().await
+    }
+
+    /// Registers a restart, or returns the one already in progress.
+    pub fn begin_restart(&self) -> RestartTicket {
+        let mut inner = self.inner.lock_or_recover();
+        if let Some(rx) = &inner.restart_in_flight {
+            return RestartTicket::Follower(rx.clone());
+        }
+        let (tx, rx) = watch::channel(None);
+        inner.restart_in_flight = Some(rx);
+        RestartTicket::Leader(tx)
+    }
+
+    /// Publishes the outcome of a restart to the callers that joined it.
+    pub fn finish_restart(&self, tx: watch::Sender<Option<RestartResult>>, result: RestartResult) {
+        self.inner.lock_or_recover().restart_in_flight = None;
+        let _ = tx.send(Some(result));
+    }
+
+    pub fn state(&self) -> ServerState {
+        self.inner.lock_or_recover().state
+    }
+
+    /// Moves to `next` and emits a "server-state" event.
+    /// Returns the previous state, or an error if the transition isn't allowed.
+    pub fn transition(
+        &self,
+        app: &AppHandle,
+        next: ServerState,
+        message: Option<String>,
+    ) -> Result<ServerState, InvalidTransition> {
+        self.transition_with_details(app, next, message, CrashDetails::default())
+    }
+
+    /// Same as [SidecarState::transition], attaching details about a crash to the event.
+    pub fn transition_with_details(
+        &self,
+        app: &AppHandle,
+        next: ServerState,
+        message: Option<String>,
+        details: CrashDetails,
+    ) -> Result<ServerState, InvalidTransition> {
+        self.enter(app, next, message, details, false)
+    }
+
+    /// Moves to Crashed whatever the current state, because the desktop app itself crashed.
+    /// Brings up the crash screen, the server keeps running until the app exits.
+    pub fn mark_desktop_crashed(
+        &self,
+        app: &AppHandle,
+        message: String,
+        diagnosis: CrashDiagnosis,
+    ) {
+        let details = CrashDetails {
+            diagnosis: Some(diagnosis),
+            ..Default::default()
+        };
+        let _ = self.enter(app, ServerState::Crashed, Some(message), details, true);
+    }
+
+    fn enter(
+        &self,
+        app: &AppHandle,
+        next: ServerState,
+        message: Option<String>,
+        details: CrashDetails,
+        force: bool,
+    ) -> Result<ServerState, InvalidTransition> {
+        let (previous, stderr, fatal) = {
+            let mut inner = self.inner.lock_or_recover();
+            let previous = inner.state;
+            if !force && !previous.can_transition_to(next) {
+                return Err(InvalidTransition {
+                    from: previous,
+                    to: next,
+                });
+            }
+            inner.state = next;
+            let (stderr, fatal) = match next {
+                ServerState::Crashed => (
+                    inner.stderr_tail.iter().cloned().collect(),
+                    inner.log_stats.last_fatal.clone(),
+                ),
+                _ => (Vec::new(), None),
+            };
+            (previous, stderr, fatal)
+        };
+
+        log_info!("Server state: {:?} -> {:?}", previous, next);
+        let payload = ServerStateEvent {
+            state: next,
+            previous,
+            message,
+            stderr,
+            fatal,
+            logs: details.logs,
+            diagnosis: details.diagnosis,
+            conflict: details.conflict,
+            will_restart: details.will_restart,
+        };
+        if next == ServerState::Crashed {
+            self.inner.lock_or_recover().last_crash = Some(payload.clone());
+        }
+        if let Err(e) = app.emit(SERVER_STATE_EVENT, payload) {
+            log_error!("Failed to emit server-state event: {}", e);
+        }
+        Ok(previous)
+    }
+
+    /// Registers the spawned sidecar. The child is handed back if another one is already registered.
+    pub fn set_child(&self, child: SidecarChild) -> Result<(), SidecarChild> {
+        let mut inner = self.inner.lock_or_recover();
+        if inner.child.is_some() {
+            return Err(child);
+        }
+        inner.child = Some(child);
+        inner.supervisor.record_launch();
+        inner.stderr_tail.clear();
+        inner.log_stats = LogStats::default();
+        Ok(())
+    }
+
+    /// Updates the log counters from a parsed line of server output.
+    pub fn record_log(&self, stream: OutputStream, log: &ParsedLog) {
+        let mut inner = self.inner.lock_or_recover();
+        let stats = &mut inner.log_stats;
+        match log.level {
+            Some(LogLevel::Warn) => stats.warnings += 1,
+            Some(LogLevel::Error) => stats.errors += 1,
+            Some(LogLevel::Fatal | LogLevel::Panic) => {
+                stats.errors += 1;
+                stats.last_fatal = Some(log.summary());
+            }
+            Some(_) => {}
+            // Go runtime panics and fatal errors are written to stderr without formatting
+            None => {
+                let is_go_fatal =
+                    log.message.starts_with("panic:") || log.message.starts_with("fatal error:");
+                if stream == OutputStream::Stderr && is_go_fatal {
+                    stats.errors += 1;
+                    stats.last_fatal = Some(log.message.clone());
+                }
+            }
+        }
+    }
+
+    pub fn last_crash(&self) -> Option<ServerStateEvent> {
+        self.inner.lock_or_recover().last_crash.clone()
+    }
+
+    pub fn log_stats(&self) -> LogStats {
+        self.inner.lock_or_recover().log_stats.clone()
+    }
+
+    pub fn push_stderr(&self, line: String) {
+        let mut inner = self.inner.lock_or_recover();
+        if inner.stderr_tail.len() == STDERR_TAIL_LINES {
+            inner.stderr_tail.pop_front();
+        }
+        inner.stderr_tail.push_back(line);
+    }
+
+    /// Takes the child out only if it is the process identified by `pid`.
+    pub fn take_child_if(&self, pid: u32) -> Option<SidecarChild> {
+        let mut inner = self.inner.lock_or_recover();
+        if inner.child.as_ref().map(|c| c.pid()) == Some(pid) {
+            inner.child.take()
+        } else {
+            None
+        }
+    }
+
+    pub fn mode(&self) -> ServerMode {
+        self.inner.lock_or_recover().mode.clone()
+    }
+
+    /// Switches between the sidecar and a remote server, taking effect on the next launch.
+    pub fn set_mode(&self, mode: ServerMode) {
+        self.inner.lock_or_recover().mode = mode;
+    }
+
+    pub fn is_remote(&self) -> bool {
+        matches!(self.inner.lock_or_recover().mode, ServerMode::Remote(_))
+    }
+
+    /// Base URL of the server, either the sidecar on its loopback port or the remote server.
+    pub fn base_url(&self) -> String {
+        let inner = self.inner.lock_or_recover();
+        match &inner.mode {
+            ServerMode::Sidecar => health::server_base_url(inner.port),
+            ServerMode::Remote(url) => url.clone(),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.inner.lock_or_recover().port
+    }
+
+    pub fn set_port(&self, port: u16) {
+        self.inner.lock_or_recover().port = port;
+    }
+
+    pub fn set_adopted(&self, pid: Option<u32>) {
+        self.inner.lock_or_recover().adopted_pid = pid;
+    }
+
+    pub fn adopted_pid(&self) -> Option<u32> {
+        self.inner.lock_or_recover().adopted_pid
+    }
+
+    pub fn take_adopted(&self) -> Option<u32> {
+        self.inner.lock_or_recover().adopted_pid.take()
+    }
+
+    /// Holds the data directory lock until [SidecarState::release_data_dir_lock] is called.
+    pub fn set_data_dir_lock(&self, lock: DataDirLock) {
+        self.inner.lock_or_recover().data_dir_lock = Some(lock);
+    }
+
+    /// Lets another process use the data directory, once the sidecar has exited.
+    pub fn release_data_dir_lock(&self) {
+        self.inner.lock_or_recover().data_dir_lock = None;
+    }
+
+    pub fn child_pid(&self) -> Option<u32> {
+        self.inner.lock_or_recover().child.as_ref().map(|c| c.pid())
+    }
+
+    /// PID of the sidecar of this session, spawned or adopted.
+    pub fn server_pid(&self) -> Option<u32> {
+        let inner = self.inner.lock_or_recover();
+        inner.child.as_ref().map(|c| c.pid()).or(inner.adopted_pid)
+    }
+
+    pub fn has_started(&self) -> bool {
+        self.inner.lock_or_recover().has_started
+    }
+
+    pub fn mark_started(&self) {
+        self.inner.lock_or_recover().has_started = true;
+    }
+
+    /// Gives access to the restart supervisor.
+    pub fn with_supervisor<T>(&self, f: impl FnOnce(&mut Supervisor) -> T) -> T {
+        f(&mut self.inner.lock_or_recover().supervisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServerState::{self, *};
+
+    const STATES: [ServerState; 7] = [
+        Starting, Ready, Unhealthy, Crashed, Restarting, Stopping, Stopped,
+    ];
+
+    /// Every transition the lifecycle allows.
+    const ALLOWED: &[(ServerState, ServerState)] = &[
+        (Stopped, Starting),
+        (Starting, Ready),
+        (Starting, Crashed),
+        (Starting, Restarting),
+        (Starting, Stopping),
+        (Ready, Unhealthy),
+        (Ready, Crashed),
+        (Ready, Restarting),
+        (Ready, Stopping),
+        (Unhealthy, Ready),
+        (Unhealthy, Crashed),
+        (Unhealthy, Restarting),
+        (Unhealthy, Stopping),
+        (Crashed, Starting),
+        (Crashed, Restarting),
+        (Crashed, Stopping),
+        (Restarting, Starting),
+        (Restarting, Crashed),
+        (Restarting, Stopping),
+        (Stopping, Stopped),
+    ];
+
+    #[test]
+    fn allows_lifecycle_transitions() {
+        for (from, to) in ALLOWED {
+            assert!(
+                from.can_transition_to(*to),
+                "{:?} -> {:?} should be allowed",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_transitions() {
+        let rejected = [
+            (Stopped, Ready),
+            (Stopped, Stopping),
+            (Crashed, Ready),
+            (Stopping, Starting),
+            (Stopping, Crashed),
+            (Restarting, Ready),
+            (Ready, Starting),
+            (Ready, Stopped),
+        ];
+        for (from, to) in rejected {
+            assert!(
+                !from.can_transition_to(to),
+                "{:?} -> {:?} should be rejected",
+                from,
+                to
+            );
+        }
+
+        // Including staying in the same state
+        for from in STATES {
+            for to in STATES {
+                if !ALLOWED.contains(&(from, to)) {
+                    assert!(
+                        !from.can_transition_to(to),
+                        "{:?} -> {:?} should be rejected",
+                        from,
+                        to
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::constants::{
+    RESTART_INITIAL_BACKOFF_MS, RESTART_MAX_BACKOFF_MS, RESTART_MAX_RETRIES,
+    RESTART_RESET_WINDOW_SECS,
+};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// When and how often a crashed sidecar should be restarted.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Maximum number of consecutive restarts before giving up
+    pub max_retries: u32,
+    /// Delay before the first restart, doubled on each consecutive attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// A sidecar that stayed up for this long is considered healthy again and the retry budget is reset
+    pub reset_window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: RESTART_MAX_RETRIES,
+            initial_backoff: Duration::from_millis(RESTART_INITIAL_BACKOFF_MS),
+            max_backoff: Duration::from_millis(RESTART_MAX_BACKOFF_MS),
+            reset_window: Duration::from_secs(RESTART_RESET_WINDOW_SECS),
+        }
+    }
+}
+
+/// Payload of the "server-restart" event sent to the webview before each restart attempt.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartAttempt {
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+/// Keeps track of consecutive restarts according to a [RestartPolicy].
+#[derive(Debug)]
+pub struct Supervisor {
+    policy: RestartPolicy,
+    attempts: u32,
+    last_launch: Option<Instant>,
+}
+
+impl Supervisor {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+            last_launch: None,
+        }
+    }
+
+    /// Records that the sidecar has just been spawned.
+    pub fn record_launch(&mut self) {
+        self.last_launch = Some(Instant::now());
+    }
+
+    /// Called when the sidecar exits unexpectedly.
+    /// Returns the next restart attempt, or None if the retry budget is exhausted.
+    pub fn on_crash(
+        &mut self,
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+    ) -> Option<RestartAttempt> {
+        // The previous run lasted long enough, start counting from scratch
+        if let Some(last_launch) = self.last_launch {
+            if last_launch.elapsed() >= self.policy.reset_window {
+                self.attempts = 0;
+            }
+        }
+
+        if self.attempts >= self.policy.max_retries {
+            return None;
+        }
+
+        let delay = self
+            .policy
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(self.attempts))
+            .min(self.policy.max_backoff);
+        self.attempts += 1;
+
+        Some(RestartAttempt {
+            attempt: self.attempts,
+            max_retries: self.policy.max_retries,
+            delay_ms: delay.as_millis() as u64,
+            exit_code,
+            signal,
+        })
+    }
+
+    /// Resets the retry budget, e.g. after a restart requested by the user.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(RestartPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_retries: u32) -> RestartPolicy {
+        RestartPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+            reset_window: Duration::from_secs(3600),
+        }
+    }
+
+    fn delays(supervisor: &mut Supervisor, crashes: usize) -> Vec<Option<u64>> {
+        (0..crashes)
+            .map(|_| {
+                supervisor
+                    .on_crash(Some(1), None)
+                    .map(|attempt| attempt.delay_ms)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let mut supervisor = Supervisor::new(policy(5));
+        supervisor.record_launch();
+        assert_eq!(
+            delays(&mut supervisor, 5),
+            [Some(100), Some(200), Some(400), Some(500), Some(500)]
+        );
+    }
+
+    #[test]
+    fn gives_up_once_the_budget_is_exhausted() {
+        let mut supervisor = Supervisor::new(policy(2));
+        supervisor.record_launch();
+        let attempts: Vec<_> = (0..3).map(|_| supervisor.on_crash(None, Some(9))).collect();
+        assert_eq!(
+            attempts[0].as_ref().map(|a| (a.attempt, a.max_retries)),
+            Some((1, 2))
+        );
+        assert_eq!(
+            attempts[1].as_ref().map(|a| (a.attempt, a.max_retries)),
+            Some((2, 2))
+        );
+        assert!(attempts[2].is_none());
+    }
+
+    #[test]
+    fn attempt_carries_the_exit_status() {
+        let mut supervisor = Supervisor::new(policy(1));
+        let attempt = supervisor.on_crash(Some(2), Some(15)).unwrap();
+        assert_eq!((attempt.exit_code, attempt.signal), (Some(2), Some(15)));
+    }
+
+    #[test]
+    fn reset_restores_the_budget() {
+        let mut supervisor = Supervisor::new(policy(1));
+        assert!(supervisor.on_crash(None, None).is_some());
+        assert!(supervisor.on_crash(None, None).is_none());
+        supervisor.reset();
+        assert_eq!(
+            supervisor.on_crash(None, None).map(|a| a.delay_ms),
+            Some(100)
+        );
+    }
+
+    #[test]
+    fn long_enough_run_restores_the_budget() {
+        let mut supervisor = Supervisor::new(RestartPolicy {
+            reset_window: Duration::ZERO,
+            ..policy(1)
+        });
+        for _ in 0..3 {
+            supervisor.record_launch();
+            assert_eq!(supervisor.on_crash(None, None).map(|a| a.attempt), Some(1));
+        }
+    }
+
+    #[test]
+    fn short_run_keeps_counting() {
+        let mut supervisor = Supervisor::new(policy(3));
+        supervisor.record_launch();
+        supervisor.on_crash(None, None);
+        supervisor.record_launch();
+        assert_eq!(supervisor.on_crash(None, None).map(|a| a.attempt), Some(2));
+    }
+}
@@ -1,11 +1,42 @@
-use crate::constants::MAIN_WINDOW_LABEL;
+use crate::error::DesktopError;
+use crate::logging::{self, log_error};
+use crate::profiles::{self, ProfilesEvent, PROFILES_EVENT};
+use crate::screens;
+use crate::state::{ServerStateEvent, SidecarState, SERVER_STATE_EVENT};
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager, Runtime,
+    AppHandle, Listener, Manager, Wry,
 };
+use tauri_plugin_opener::OpenerExt;
 
-pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
+/// Prefix of the ids of the items in the profile submenu, followed by the profile name
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+/// Replaces the items of the profile submenu, checking the active profile.
+fn fill_profile_menu(
+    app: &AppHandle,
+    submenu: &Submenu<Wry>,
+    payload: &ProfilesEvent,
+) -> tauri::Result<()> {
+    while submenu.remove_at(0)?.is_some() {}
+    for name in &payload.profiles {
+        let item = CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROFILE_ITEM_PREFIX, name),
+            name,
+            true,
+            *name == payload.active,
+            None::<&str>,
+        )?;
+        submenu.append(&item)?;
+    }
+    Ok(())
+}
+
+pub fn create_tray(app: &AppHandle) -> Result<(), DesktopError> {
+    let server_status_i =
+        MenuItem::with_id(app, "server_status", "Server: Stopped", false, None::<&str>)?;
     let quit_i = MenuItem::with_id(app, "quit", "Quit Seanime", true, None::<&str>)?;
     // let restart_i = MenuItem::with_id(app, "restart", "Restart Seanime", true, None::<&str>)?;
     // let open_web_i = MenuItem::with_id(app, "open_web", "Open Web UI", true, None::<&str>)?;
@@ -16,6 +47,10 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
         true,
         None::<&str>,
     )?;
+    let open_logs_i = MenuItem::with_id(app, "open_logs", "Open logs", true, None::<&str>)?;
+    let profile_i = Submenu::with_id(app, "profile", "Profile", true)?;
+    fill_profile_menu(app, &profile_i, &profiles::profiles_of(app))?;
+    #[cfg(target_os = "macos")]
     let accessory_mode_i = MenuItem::with_id(
         app,
         "accessory_mode",
@@ -23,61 +58,34 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
         true,
         None::<&str>,
     )?;
-    let mut items: Vec<&dyn tauri::menu::IsMenuItem<R>> = vec![&toggle_visibility_i, &quit_i];
+    let items: &[&dyn tauri::menu::IsMenuItem<Wry>] = &[
+        &server_status_i,
+        &toggle_visibility_i,
+        #[cfg(target_os = "macos")]
+        &accessory_mode_i,
+        &profile_i,
+        &open_logs_i,
+        &quit_i,
+    ];
 
-    #[cfg(target_os = "macos")]
-    {
-        items = vec![&toggle_visibility_i, &accessory_mode_i, &quit_i];
-    }
-
-    let menu = Menu::with_items(app, &items)?;
+    let menu = Menu::with_items(app, items)?;
 
     let _ = TrayIconBuilder::with_id("tray")
-        .icon(app.default_window_icon().unwrap().clone())
+        .icon(
+            app.default_window_icon()
+                .ok_or(DesktopError::MissingIcon)?
+                .clone(),
+        )
         .menu(&menu)
-        .menu_on_left_click(false)
-        .on_menu_event(move |app, event| match event.id.as_ref() {
-            "quit" => {
-                app.exit(0);
-            }
-            // "restart" => app.restart(),
-            "toggle_visibility" => {
-                if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
-                    if !window.is_visible().unwrap() {
-                        let _ = window.show();
-                        let _ = window.set_focus();
-                        #[cfg(target_os = "macos")]
-                        app.set_activation_policy(tauri::ActivationPolicy::Regular)
-                            .unwrap();
-                    } else {
-                        let _ = window.hide();
-                        #[cfg(target_os = "macos")]
-                        app.set_activation_policy(tauri::ActivationPolicy::Accessory)
-                            .unwrap();
-                    }
-                }
-            }
-            "accessory_mode" => {
-                #[cfg(target_os = "macos")]
-                app.set_activation_policy(tauri::ActivationPolicy::Accessory)
-                    .unwrap();
+        .show_menu_on_left_click(false)
+        .on_menu_event(move |app, event| {
+            if let Err(e) = handle_menu_event(app, event.id.as_ref()) {
+                log_error!(
+                    "Failed to handle tray menu item {:?}: {}",
+                    event.id.as_ref(),
+                    e
+                );
             }
-            // "hide" => {
-            //     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
-            //         if window.is_minimized().unwrap() {
-            //             let _ = window.show();
-            //             let _ = window.set_focus();
-            //             #[cfg(target_os = "macos")]
-            //             app.set_activation_policy(tauri::ActivationPolicy::Regular).unwrap();
-            //         } else {
-            //             let _ = window.hide();
-            //             #[cfg(target_os = "macos")]
-            //             app.set_activation_policy(tauri::ActivationPolicy::Accessory).unwrap();
-            //         }
-            //     }
-            // }
-            // Add more events here
-            _ => {}
         })
         .on_tray_icon_event(|tray, event| {
             if let TrayIconEvent::Click {
@@ -86,17 +94,91 @@ pub fn create_tray<R: Runtime>(app: &tauri::AppHandle<R>) -> tauri::Result<()> {
                 ..
             } = event
             {
-                let app = tray.app_handle();
-                if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                    #[cfg(target_os = "macos")]
-                    app.set_activation_policy(tauri::ActivationPolicy::Regular)
-                        .unwrap();
+                if let Err(e) = screens::show_main_window(tray.app_handle()) {
+                    log_error!("Failed to show the main window: {}", e);
                 }
             }
         })
-        .build(app);
+        .build(app)?;
 
+    // Reflect the profiles in the menu
+    let app_handle = app.clone();
+    app.listen(PROFILES_EVENT, move |event| {
+        if let Ok(payload) = serde_json::from_str::<ProfilesEvent>(event.payload()) {
+            if let Err(e) = fill_profile_menu(&app_handle, &profile_i, &payload) {
+                log_error!("Failed to update the profile menu: {}", e);
+            }
+        }
+    });
+
+    // Reflect the server state in the menu
+    let app_handle = app.clone();
+    app.listen(SERVER_STATE_EVENT, move |event| {
+        if let Ok(payload) = serde_json::from_str::<ServerStateEvent>(event.payload()) {
+            let name = if app_handle.state::<SidecarState>().is_remote() {
+                "Remote server"
+            } else {
+                "Server"
+            };
+            let _ = server_status_i.set_text(format!("{}: {}", name, payload.state.label()));
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_menu_event(app: &AppHandle, id: &str) -> Result<(), DesktopError> {
+    match id {
+        "quit" => {
+            app.exit(0);
+        }
+        // "restart" => app.restart(),
+        "open_logs" => {
+            if let Some(dir) = logging::log_dir() {
+                if let Err(e) = app.opener().open_path(dir.to_string_lossy(), None::<&str>) {
+                    log_error!("Failed to open log directory: {}", e);
+                }
+            }
+        }
+        "toggle_visibility" => {
+            if screens::main_window(app)?.is_visible()? {
+                screens::hide_main_window(app)?;
+            } else {
+                screens::show_main_window(app)?;
+            }
+        }
+        "accessory_mode" => {
+            #[cfg(target_os = "macos")]
+            app.set_activation_policy(tauri::ActivationPolicy::Accessory)?;
+        }
+        // "hide" => {
+        //     if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+        //         if window.is_minimized().unwrap() {
+        //             let _ = window.show();
+        //             let _ = window.set_focus();
+        //             #[cfg(target_os = "macos")]
+        //             app.set_activation_policy(tauri::ActivationPolicy::Regular).unwrap();
+        //         } else {
+        //             let _ = window.hide();
+        //             #[cfg(target_os = "macos")]
+        //             app.set_activation_policy(tauri::ActivationPolicy::Accessory).unwrap();
+        //         }
+        //     }
+        // }
+        // Add more events here
+        id => {
+            if let Some(name) = id.strip_prefix(PROFILE_ITEM_PREFIX) {
+                let app = app.clone();
+                let name = name.to_string();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = profiles::switch_profile(&app, &name).await {
+                        log_error!("Failed to switch to profile {:?}: {}", name, e);
+                    }
+                    // Clicking an item toggles its check mark, restore the actual state
+                    profiles::emit_profiles(&app);
+                });
+            }
+        }
+    }
     Ok(())
 }
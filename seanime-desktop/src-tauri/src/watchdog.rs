@@ -0,0 +1,124 @@
+use crate::constants::{
+    HEALTH_CHECK_FAILURE_THRESHOLD, HEALTH_CHECK_INTERVAL_SECS, HEALTH_CHECK_TIMEOUT_MS,
+};
+use crate::health;
+use crate::logging::{log_error, log_info};
+use crate::server;
+use crate::state::{ServerState, SidecarState};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::time::sleep;
+
+/// How the running server is checked for liveness.
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    pub interval: Duration,
+    pub request_timeout: Duration,
+    /// Consecutive failed checks after which the server is marked unhealthy
+    pub failure_threshold: u32,
+    /// Whether an unhealthy server is restarted
+    pub auto_restart: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS),
+            request_timeout: Duration::from_millis(HEALTH_CHECK_TIMEOUT_MS),
+            failure_threshold: HEALTH_CHECK_FAILURE_THRESHOLD,
+            auto_restart: true,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Default config, overridable through `SEANIME_DESKTOP_HEALTH_INTERVAL` (seconds),
+    /// `SEANIME_DESKTOP_HEALTH_FAILURES` and `SEANIME_DESKTOP_HEALTH_AUTO_RESTART` ("true" or "false").
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(secs) = std::env::var("SEANIME_DESKTOP_HEALTH_INTERVAL")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+        {
+            config.interval = Duration::from_secs(secs.max(1));
+        }
+        if let Some(failures) = std::env::var("SEANIME_DESKTOP_HEALTH_FAILURES")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+        {
+            config.failure_threshold = failures.max(1);
+        }
+        if let Some(auto_restart) = std::env::var("SEANIME_DESKTOP_HEALTH_AUTO_RESTART")
+            .ok()
+            .and_then(|s| s.parse::<bool>().ok())
+        {
+            config.auto_restart = auto_restart;
+        }
+        config
+    }
+}
+
+/// Periodically checks the status endpoint of the server process identified by `pid`,
+/// or of the remote server if `pid` is None.
+/// Stops once that process is no longer the running one.
+pub fn spawn(app: AppHandle, pid: Option<u32>) {
+    let config = WatchdogConfig::from_env();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<SidecarState>();
+        let client = match reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                log_error!("Failed to create the health check client: {}", e);
+                return;
+            }
+        };
+        let base_url = state.base_url();
+        let mut failures = 0;
+
+        loop {
+            sleep(config.interval).await;
+
+            let is_running = matches!(state.state(), ServerState::Ready | ServerState::Unhealthy);
+            if !is_running || state.server_pid() != pid {
+                return;
+            }
+
+            if health::check_status(&client, &base_url).await {
+                if failures >= config.failure_threshold {
+                    log_info!("Seanime server is responding again");
+                    let _ = state.transition(&app, ServerState::Ready, None);
+                }
+                failures = 0;
+                continue;
+            }
+
+            failures += 1;
+            if failures != config.failure_threshold {
+                continue;
+            }
+
+            log_error!(
+                "Seanime server failed {} consecutive health checks",
+                config.failure_threshold
+            );
+            let message = format!(
+                "The server stopped responding ({} failed health checks).",
+                config.failure_threshold
+            );
+            if state
+                .transition(&app, ServerState::Unhealthy, Some(message))
+                .is_err()
+            {
+                return;
+            }
+            // A remote server can't be restarted, keep checking until it recovers
+            if let (true, Some(pid)) = (config.auto_restart, pid) {
+                server::restart_unhealthy_server(&app, pid).await;
+                return;
+            }
+        }
+    });
+}
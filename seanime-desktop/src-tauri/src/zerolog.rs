@@ -0,0 +1,313 @@
+use crate::log_buffer::LogLevel;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A line of the server's console output, parsed.
+///
+/// The server logs through zerolog's console writer, which (once colors are stripped) writes lines like
+/// `2006-01-02 15:04:05 INF - module > message key=value error="some error"`.
+/// Lines that don't follow this format are kept as a plain message.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedLog {
+    /// Timestamp as written by the server, in its local time
+    pub time: Option<String>,
+    pub level: Option<LogLevel>,
+    /// Part of the message before " > ", e.g. "app" or "ws"
+    pub module: Option<String>,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl ParsedLog {
+    /// Message with the `error` field appended, if any.
+    pub fn summary(&self) -> String {
+        let message = match &self.module {
+            Some(module) => format!("{}: {}", module, self.message),
+            None => self.message.clone(),
+        };
+        match self.fields.get("error") {
+            Some(error) => format!("{} ({})", message, error),
+            None => message,
+        }
+    }
+}
+
+pub fn parse_line(line: &str) -> ParsedLog {
+    let mut rest = line.trim();
+    let mut parsed = ParsedLog::default();
+
+    // Timestamp
+    if let Some((date, after_date)) = rest.split_once(' ') {
+        if let Some((time, after_time)) = after_date.split_once(' ') {
+            if is_date(date) && is_time(time) {
+                parsed.time = Some(format!("{} {}", date, time));
+                rest = after_time.trim_start();
+            }
+        }
+    }
+
+    // Level, followed by a " -" separator in the pretty format
+    let (token, after_level) = rest.split_once(' ').unwrap_or((rest, ""));
+    if let Some(level) = LogLevel::from_token(token) {
+        parsed.level = Some(level);
+        rest = after_level.trim_start();
+        rest = rest.strip_prefix('-').map(str::trim_start).unwrap_or(rest);
+    }
+
+    // Trailing key=value fields, only written on lines with a level. Other lines such as
+    // "[signal SIGSEGV: segmentation violation code=0x1 addr=0x0 pc=0x10d3f4e]" are kept whole.
+    let tokens = if parsed.level.is_some() {
+        tokenize(rest)
+    } else {
+        Vec::new()
+    };
+    let mut message_end = rest.len();
+    for (start, token) in tokens.iter().rev() {
+        match parse_field(token) {
+            Some((key, value)) => {
+                parsed.fields.insert(key, value);
+                message_end = *start;
+            }
+            None => break,
+        }
+    }
+    let message = rest[..message_end].trim_end();
+
+    match message.split_once(" > ") {
+        Some((module, message)) if !module.contains(' ') => {
+            parsed.module = Some(module.to_string());
+            parsed.message = message.to_string();
+        }
+        _ => parsed.message = message.to_string(),
+    }
+
+    parsed
+}
+
+fn is_date(s: &str) -> bool {
+    s.len() == 10
+        && s.chars().enumerate().all(|(i, c)| match i {
+            4 | 7 => c == '-',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+fn is_time(s: &str) -> bool {
+    s.len() == 8
+        && s.chars().enumerate().all(|(i, c)| match i {
+            2 | 5 => c == ':',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Splits on whitespace outside of double quotes, returning each token with its byte offset.
+fn tokenize(s: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        if start.is_none() {
+            if c.is_whitespace() {
+                continue;
+            }
+            start = Some(i);
+        }
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && in_quotes {
+            escaped = true;
+        } else if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c.is_whitespace() && !in_quotes {
+            if let Some(token_start) = start.take() {
+                tokens.push((token_start, &s[token_start..i]));
+            }
+        }
+    }
+    if let Some(token_start) = start {
+        tokens.push((token_start, &s[token_start..]));
+    }
+    tokens
+}
+
+fn parse_field(token: &str) -> Option<(String, String)> {
+    let (key, value) = token.split_once('=')?;
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if !is_key {
+        return None;
+    }
+    Some((key.to_string(), unquote(value)))
+}
+
+fn unquote(value: &str) -> String {
+    let inner = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner,
+        None => return value.to_string(),
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::decode_line;
+
+    /// Expected time, level, module and message of a parsed line.
+    type Expected<'a> = (Option<&'a str>, Option<LogLevel>, Option<&'a str>, &'a str);
+
+    fn assert_parsed(line: &str, parsed: &ParsedLog, (time, level, module, message): Expected) {
+        assert_eq!(parsed.time.as_deref(), time, "time of {:?}", line);
+        assert_eq!(parsed.level, level, "level of {:?}", line);
+        assert_eq!(parsed.module.as_deref(), module, "module of {:?}", line);
+        assert_eq!(parsed.message, message, "message of {:?}", line);
+    }
+
+    #[test]
+    fn parses_each_level() {
+        let levels = [
+            ("TRC", LogLevel::Trace),
+            ("DBG", LogLevel::Debug),
+            ("INF", LogLevel::Info),
+            ("WRN", LogLevel::Warn),
+            ("ERR", LogLevel::Error),
+            ("FTL", LogLevel::Fatal),
+            ("PNC", LogLevel::Panic),
+        ];
+        for (token, level) in levels {
+            // Console format, then the format of the log file
+            for line in [
+                format!("2024-05-06 07:08:09 {} - app > Something happened", token),
+                format!("2024-05-06 07:08:09 |{}| app > Something happened", token),
+            ] {
+                let expected = (
+                    Some("2024-05-06 07:08:09"),
+                    Some(level),
+                    Some("app"),
+                    "Something happened",
+                );
+                assert_parsed(&line, &parse_line(&line), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_colored_lines() {
+        let cases: &[(&str, Expected)] = &[
+            (
+                "\x1b[90m2024-05-06 07:08:09\x1b[0m \x1b[1mINF\x1b[0m\x1b[90m -\x1b[0m \x1b[36mapp\x1b[0m\x1b[90m >\x1b[0m Seanime started at 127.0.0.1:43211\r\n",
+                (Some("2024-05-06 07:08:09"), Some(LogLevel::Info), Some("app"), "Seanime started at 127.0.0.1:43211"),
+            ),
+            (
+                "\x1b[90m2024-05-06 07:08:09\x1b[0m \x1b[31mERR\x1b[0m\x1b[90m -\x1b[0m \x1b[36mdb\x1b[0m\x1b[90m >\x1b[0m Failed to open \x1b[36merror=\x1b[0m\x1b[31m\"database is locked\"\x1b[0m",
+                (Some("2024-05-06 07:08:09"), Some(LogLevel::Error), Some("db"), "Failed to open"),
+            ),
+        ];
+        for (colored, expected) in cases {
+            let line = decode_line(colored.as_bytes().to_vec());
+            assert_parsed(&line, &parse_line(&line), *expected);
+        }
+
+        let line = decode_line(cases[1].0.as_bytes().to_vec());
+        assert_eq!(
+            parse_line(&line).summary(),
+            "db: Failed to open (database is locked)"
+        );
+    }
+
+    #[test]
+    fn parses_fields() {
+        let line = r#"2024-05-06 07:08:09 WRN - extensions > Failed to load id=anilist-sync count=2 error="invalid manifest: \"name\" is missing""#;
+        let parsed = parse_line(line);
+        let expected = (
+            Some("2024-05-06 07:08:09"),
+            Some(LogLevel::Warn),
+            Some("extensions"),
+            "Failed to load",
+        );
+        assert_parsed(line, &parsed, expected);
+        assert_eq!(
+            parsed.fields.get("id").map(String::as_str),
+            Some("anilist-sync")
+        );
+        assert_eq!(parsed.fields.get("count").map(String::as_str), Some("2"));
+        assert_eq!(
+            parsed.fields.get("error").map(String::as_str),
+            Some(r#"invalid manifest: "name" is missing"#)
+        );
+    }
+
+    #[test]
+    fn parses_a_multi_line_stack_trace() {
+        let trace = [
+            "panic: runtime error: invalid memory address or nil pointer dereference",
+            "[signal SIGSEGV: segmentation violation code=0x1 addr=0x0 pc=0x10d3f4e]",
+            "",
+            "goroutine 1 [running]:",
+            "seanime/internal/core.(*App).InitOrRefreshModules(0xc000132000)",
+            "\t/app/internal/core/modules.go:42 +0x2e",
+            "exit status 2",
+        ];
+        // Each line is kept whole, without a level or fields
+        for line in trace {
+            let parsed = parse_line(line);
+            assert_parsed(line, &parsed, (None, None, None, line.trim()));
+            assert!(parsed.fields.is_empty(), "fields of {:?}", line);
+        }
+
+        // A stack trace in a field keeps its line breaks
+        let line = r#"2024-05-06 07:08:09 ERR - app > Recovered from panic stack="main.main()\n\tmain.go:12 +0x1d""#;
+        let parsed = parse_line(line);
+        assert_eq!(parsed.level, Some(LogLevel::Error));
+        assert_eq!(
+            parsed.fields.get("stack").map(String::as_str),
+            Some("main.main()\n\tmain.go:12 +0x1d")
+        );
+    }
+
+    #[test]
+    fn keeps_other_lines_as_messages() {
+        let cases: &[(&str, Expected)] = &[
+            ("Starting Seanime", (None, None, None, "Starting Seanime")),
+            (
+                "INFO something happened",
+                (None, None, None, "INFO something happened"),
+            ),
+            (
+                "2024-05-06 not a time",
+                (None, None, None, "2024-05-06 not a time"),
+            ),
+            ("a > b > c", (None, None, Some("a"), "b > c")),
+            (
+                "not a module > message",
+                (None, None, None, "not a module > message"),
+            ),
+            ("ERR", (None, Some(LogLevel::Error), None, "")),
+            ("", (None, None, None, "")),
+        ];
+        for (line, expected) in cases {
+            let parsed = parse_line(line);
+            assert_parsed(line, &parsed, *expected);
+            assert!(parsed.fields.is_empty(), "fields of {:?}", line);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Checks that the sidecar and the processes it starts don't outlive the desktop process on Linux.
+#![cfg(target_os = "linux")]
+
+use app_lib::process_group;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Set when the test binary is re-executed to act as the desktop process
+const ROLE_ENV: &str = "SEANIME_TEST_PROCESS_GROUP_ROLE";
+const PIDS_FILE_ENV: &str = "SEANIME_TEST_PIDS_FILE";
+
+/// Fake sidecar: starts a long-running process like mpv would, records both pids and waits
+const FAKE_SIDECAR_SCRIPT: &str =
+    "sleep 300 & echo $$ $! > \"$PIDS_FILE.tmp\" && mv \"$PIDS_FILE.tmp\" \"$PIDS_FILE\"; wait";
+
+fn fake_sidecar(pids_file: &Path) -> Command {
+    let mut command = Command::new("/bin/sh");
+    command
+        .args(["-c", FAKE_SIDECAR_SCRIPT])
+        .env("PIDS_FILE", pids_file);
+    command
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("seanime-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Returns true if the process exists and isn't a zombie waiting to be reaped.
+fn is_alive(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .is_some_and(|state| state != "Z" && state != "X"),
+        Err(_) => false,
+    }
+}
+
+fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if condition() {
+            return true;
+        }
+        sleep(Duration::from_millis(50));
+    }
+    condition()
+}
+
+fn read_pids(pids_file: &Path) -> Vec<u32> {
+    assert!(
+        wait_until(Duration::from_secs(10), || pids_file.exists()),
+        "the fake sidecar did not start"
+    );
+    let pids: Vec<u32> = fs::read_to_string(pids_file)
+        .unwrap()
+        .split_whitespace()
+        .map(|pid| pid.parse().unwrap())
+        .collect();
+    assert_eq!(pids.len(), 2);
+    pids
+}
+
+/// Spawns the fake sidecar and stays alive until killed by the test.
+fn run_fake_desktop() {
+    let pids_file = PathBuf::from(std::env::var(PIDS_FILE_ENV).unwrap());
+    let (_rx, _child) = process_group::spawn(fake_sidecar(&pids_file)).unwrap();
+    loop {
+        sleep(Duration::from_secs(1));
+    }
+}
+
+#[test]
+fn sidecar_group_dies_with_desktop_process() {
+    if std::env::var(ROLE_ENV).is_ok() {
+        run_fake_desktop();
+        return;
+    }
+
+    let dir = temp_dir("desktop-killed");
+    let pids_file = dir.join("pids");
+    let mut desktop = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "sidecar_group_dies_with_desktop_process",
+            "--nocapture",
+        ])
+        .env(ROLE_ENV, "desktop")
+        .env(PIDS_FILE_ENV, &pids_file)
+        .spawn()
+        .unwrap();
+
+    let pids = read_pids(&pids_file);
+    assert!(pids.iter().all(|pid| is_alive(*pid)));
+
+    // Simulate a crash of the desktop process
+    desktop.kill().unwrap();
+    desktop.wait().unwrap();
+
+    let leaked = || {
+        pids.iter()
+            .copied()
+            .filter(|pid| is_alive(*pid))
+            .collect::<Vec<_>>()
+    };
+    assert!(
+        wait_until(Duration::from_secs(5), || leaked().is_empty()),
+        "processes outlived the desktop process: {:?}",
+        leaked()
+    );
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn dropping_the_handle_kills_the_group() {
+    if std::env::var(ROLE_ENV).is_ok() {
+        return;
+    }
+
+    let dir = temp_dir("handle-dropped");
+    let pids_file = dir.join("pids");
+    let (_rx, child) = process_group::spawn(fake_sidecar(&pids_file)).unwrap();
+
+    let pids = read_pids(&pids_file);
+    assert_eq!(pids[0], child.pid());
+    drop(child);
+
+    let leaked = || {
+        pids.iter()
+            .copied()
+            .filter(|pid| is_alive(*pid))
+            .collect::<Vec<_>>()
+    };
+    assert!(
+        wait_until(Duration::from_secs(5), || leaked().is_empty()),
+        "processes outlived the handle: {:?}",
+        leaked()
+    );
+    let _ = fs::remove_dir_all(dir);
+}
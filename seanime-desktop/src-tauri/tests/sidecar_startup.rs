@@ -0,0 +1,114 @@
+//! Checks that the desktop binary goes through `app_lib::run` and starts the sidecar.
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const DESKTOP_EXE: &str = env!("CARGO_BIN_EXE_seanime-desktop");
+/// Directory of the app's local data, under XDG_DATA_HOME
+const APP_IDENTIFIER: &str = "app.seanime.desktop";
+/// Written by the desktop process once it spawned the sidecar
+const RUNTIME_FILE_NAME: &str = "sidecar.json";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("seanime-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Returns true if the process exists and isn't a zombie waiting to be reaped.
+fn is_alive(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .is_some_and(|state| state != "Z" && state != "X"),
+        Err(_) => false,
+    }
+}
+
+fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if condition() {
+            return true;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    condition()
+}
+
+fn read_sidecar_pid(runtime_file: &Path) -> Option<u32> {
+    let record: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(runtime_file).ok()?).ok()?;
+    record.get("pid")?.as_u64().map(|pid| pid as u32)
+}
+
+/// Set by CI, which provides a display and the sidecar binary
+const RUN_ENV: &str = "SEANIME_DESKTOP_TEST_SIDECAR";
+
+/// Returns false if the test wasn't asked for, and fails it if it was but the environment can't run it.
+fn check_prerequisites() -> bool {
+    if std::env::var_os(RUN_ENV).is_none() {
+        eprintln!(
+            "skipping: set {}=1 to start the desktop binary and its sidecar",
+            RUN_ENV
+        );
+        return false;
+    }
+    assert!(
+        std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some(),
+        "{} is set but no display is available, set DISPLAY or WAYLAND_DISPLAY (e.g. through xvfb-run)",
+        RUN_ENV
+    );
+    // The sidecar is copied next to the desktop binary when it is built from binaries/
+    let sidecar = Path::new(DESKTOP_EXE).with_file_name("seanime");
+    assert!(
+        sidecar.exists(),
+        "{} is set but there is no sidecar at {}, build the server into binaries/ first",
+        RUN_ENV,
+        sidecar.display()
+    );
+    true
+}
+
+#[test]
+fn binary_starts_the_sidecar() {
+    if !check_prerequisites() {
+        return;
+    }
+
+    // Keep the settings, data and logs of the test apart from the user's
+    let home = temp_dir("desktop-startup");
+    let data_home = home.join("data");
+    let mut desktop = Command::new(DESKTOP_EXE)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", &data_home)
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("SEANIME_DESKTOP_PORT_STRATEGY", "random")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let runtime_file = data_home.join(APP_IDENTIFIER).join(RUNTIME_FILE_NAME);
+    let started = wait_until(Duration::from_secs(30), || {
+        read_sidecar_pid(&runtime_file).is_some()
+    });
+    let sidecar_pid = read_sidecar_pid(&runtime_file);
+
+    let _ = desktop.kill();
+    let _ = desktop.wait();
+
+    assert!(started, "the desktop binary did not start the sidecar");
+    let sidecar_pid = sidecar_pid.unwrap();
+    assert!(
+        wait_until(Duration::from_secs(5), || !is_alive(sidecar_pid)),
+        "the sidecar outlived the desktop process"
+    );
+    let _ = fs::remove_dir_all(home);
+}
@@ -1,12 +1,20 @@
 import { __DEV_SERVER_PORT } from "@/lib/server/config"
 
+declare global {
+    interface Window {
+        // Set by the desktop app once the server is running, the server may not be on the default port
+        __SEANIME_SERVER_BASE_URL__?: string
+    }
+}
+
 function devOrProd(dev: string, prod: string): string {
     return process.env.NODE_ENV === "development" ? dev : prod
 }
 
 export function getServerBaseUrl(removeProtocol: boolean = false): string {
     if (process.env.NEXT_PUBLIC_PLATFORM === "desktop") {
-        let ret = devOrProd(`http://127.0.0.1:${__DEV_SERVER_PORT}`, "http://127.0.0.1:43211")
+        const desktopBaseUrl = typeof window !== "undefined" ? window.__SEANIME_SERVER_BASE_URL__ : undefined
+        let ret = devOrProd(`http://127.0.0.1:${__DEV_SERVER_PORT}`, desktopBaseUrl || "http://127.0.0.1:43211")
         if (removeProtocol) {
             ret = ret.replace("http://", "").replace("https://", "")
         }
@@ -21,3 +29,15 @@ export function getServerBaseUrl(removeProtocol: boolean = false): string {
     }
     return ret
 }
+
+/**
+ * Returns the URL of a websocket endpoint of the server, e.g. "/events".
+ * The scheme follows the server's own (https -> wss), which may differ from the page's in the desktop app.
+ */
+export function getServerWebSocketUrl(path: string): string {
+    const baseUrl = getServerBaseUrl()
+    if (baseUrl.startsWith("https://")) {
+        return `wss://${baseUrl.slice("https://".length)}${path}`
+    }
+    return `ws://${baseUrl.replace("http://", "")}${path}`
+}
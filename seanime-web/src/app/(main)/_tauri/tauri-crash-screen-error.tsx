@@ -1,25 +1,236 @@
-import { emit, listen } from "@tauri-apps/api/event"
+import { Button } from "@/components/ui/button"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
 import React from "react"
 
+export type TauriServerState = "starting" | "ready" | "unhealthy" | "crashed" | "restarting" | "stopping" | "stopped"
+
+export type TauriCrashCategory =
+    | "portInUse"
+    | "dataDirInUse"
+    | "databaseLocked"
+    | "databaseCorrupt"
+    | "migrationFailed"
+    | "permissionDenied"
+    | "diskFull"
+    | "missingBinary"
+    | "outOfMemory"
+    | "killed"
+    | "panic"
+    | "startupTimeout"
+    | "unresponsive"
+    | "unreachable"
+    | "desktopPanic"
+    | "unknown"
+
+export type TauriCrashDiagnosis = {
+    category: TauriCrashCategory
+    title: string
+    explanation: string
+    actions: string[]
+    exitCode: number | null
+    signal: number | null
+    evidence: string | null
+}
+
+export type TauriServerStateEvent = {
+    state: TauriServerState
+    previous: TauriServerState
+    message: string | null
+    stderr?: string[]
+    fatal?: string
+    logs?: string[]
+    diagnosis?: TauriCrashDiagnosis
+    conflict?: TauriDataDirConflict
+    willRestart: boolean
+}
+
+export type TauriDataDirLockOwner = {
+    pid: number
+    kind: "desktop" | "server"
+    host?: string
+    port: number
+    profile?: string
+    startedAt: number
+}
+
+export type TauriDataDirConflict = {
+    dataDir: string
+    owner: TauriDataDirLockOwner | null
+    serverUrl: string | null
+    profiles: string[]
+}
+
 export function TauriCrashScreenError() {
 
     const [msg, setMsg] = React.useState("")
+    const [stderr, setStderr] = React.useState<string[]>([])
+    const [fatal, setFatal] = React.useState<string | null>(null)
+    const [logs, setLogs] = React.useState<string[]>([])
+    const [diagnosis, setDiagnosis] = React.useState<TauriCrashDiagnosis | null>(null)
+    const [canRetry, setCanRetry] = React.useState(false)
+    const [isRetrying, setIsRetrying] = React.useState(false)
+    const [hasCopied, setHasCopied] = React.useState(false)
+    // Set when another process uses the data directory
+    const [conflict, setConflict] = React.useState<TauriDataDirConflict | null>(null)
+
+    const showCrash = (event: TauriServerStateEvent) => {
+        if (event.message) setMsg(event.message)
+        setStderr(event.stderr ?? [])
+        setFatal(event.fatal ?? null)
+        setLogs(event.logs ?? [])
+        setDiagnosis(event.diagnosis ?? null)
+        setConflict(event.conflict ?? null)
+        // The desktop app itself crashed, restarting the server wouldn't help
+        setCanRetry(event.diagnosis?.category !== "desktopPanic")
+        setIsRetrying(false)
+    }
 
     React.useEffect(() => {
-        emit("crash-screen-loaded").then(() => {})
+        // The window is created when the server crashes, after the event was sent
+        invoke<TauriServerStateEvent | null>("get_last_crash").then((event) => {
+            if (event) showCrash(event)
+        })
 
-        const u = listen<string>("crash", (event) => {
-            console.log("Received crash event", event.payload)
-            setMsg(event.payload)
+        const u = listen<TauriServerStateEvent>("server-state", (event) => {
+            if (event.payload.state === "crashed" && !event.payload.willRestart) {
+                showCrash(event.payload)
+            }
         })
         return () => {
             u.then((f) => f())
         }
     }, [])
 
+    const handleRetry = () => {
+        setIsRetrying(true)
+        invoke("server_restart").catch((error) => {
+            console.error("Failed to restart server:", error)
+            setIsRetrying(false)
+        })
+    }
+
+    const handleConnect = (url: string) => {
+        setIsRetrying(true)
+        invoke("connect_to_server", { url }).catch((error) => {
+            console.error("Failed to connect to server:", error)
+            setIsRetrying(false)
+        })
+    }
+
+    const handleSwitchProfile = (name: string) => {
+        setIsRetrying(true)
+        invoke("switch_profile", { name }).catch((error) => {
+            console.error("Failed to switch profile:", error)
+            setIsRetrying(false)
+        })
+    }
+
+    const handleCopyDiagnostics = () => {
+        invoke<string>("copy_diagnostics").then(() => {
+            setHasCopied(true)
+            setTimeout(() => setHasCopied(false), 2000)
+        }).catch((error) => {
+            console.error("Failed to copy diagnostics:", error)
+        })
+    }
+
+    const handleAction = (command: string) => {
+        invoke(command).catch((error) => {
+            console.error(`Failed to run ${command}:`, error)
+        })
+    }
+
     return (
-        <p>
-            {msg || "An error occurred"}
-        </p>
+        <>
+            {diagnosis && (
+                <h3 className="text-xl font-bold">
+                    {diagnosis.title}
+                </h3>
+            )}
+            <p>
+                {msg || "An error occurred"}
+            </p>
+            {diagnosis && diagnosis.actions.length > 0 && (
+                <ul className="max-w-2xl text-sm text-left list-disc pl-5">
+                    {diagnosis.actions.map(action => <li key={action}>{action}</li>)}
+                </ul>
+            )}
+            {diagnosis?.evidence && diagnosis.evidence !== fatal && (
+                <p className="max-w-2xl text-sm text-[--red]">
+                    {diagnosis.evidence}
+                </p>
+            )}
+            {fatal && (
+                <p className="max-w-2xl text-sm text-[--red]">
+                    {fatal}
+                </p>
+            )}
+            {stderr.length > 0 && (
+                <pre className="max-w-2xl max-h-60 overflow-auto text-xs text-left text-[--muted] whitespace-pre-wrap">
+                    {stderr.join("\n")}
+                </pre>
+            )}
+            {logs.length > 0 && (
+                <pre className="max-w-2xl max-h-60 overflow-auto text-xs text-left text-[--muted] whitespace-pre-wrap">
+                    {logs.join("\n")}
+                </pre>
+            )}
+            {conflict && (
+                <div className="space-y-2 flex flex-col items-center">
+                    <p className="max-w-2xl text-sm text-[--muted]">
+                        Data directory: {conflict.dataDir}
+                    </p>
+                    {conflict.serverUrl && (
+                        <Button
+                            onClick={() => handleConnect(conflict.serverUrl!)}
+                            loading={isRetrying}
+                            intent="white"
+                            size="lg"
+                            className="rounded-full"
+                        >
+                            Connect to the server at {conflict.serverUrl}
+                        </Button>
+                    )}
+                    {conflict.profiles.map(name => (
+                        <Button
+                            key={name}
+                            onClick={() => handleSwitchProfile(name)}
+                            loading={isRetrying}
+                            intent="white-outline"
+                            size="lg"
+                            className="rounded-full"
+                        >
+                            Use profile "{name}"
+                        </Button>
+                    ))}
+                </div>
+            )}
+            {canRetry && (
+                <Button
+                    onClick={handleRetry}
+                    loading={isRetrying}
+                    intent="white-outline"
+                    size="lg"
+                    className="rounded-full"
+                >
+                    Retry
+                </Button>
+            )}
+            <div className="flex flex-wrap gap-2 justify-center">
+                <Button onClick={() => handleAction("open_log_dir")} intent="gray-outline" size="sm" className="rounded-full">
+                    Open logs
+                </Button>
+                <Button onClick={() => handleAction("open_data_dir")} intent="gray-outline" size="sm" className="rounded-full">
+                    Open data folder
+                </Button>
+                <Button onClick={handleCopyDiagnostics} intent="gray-outline" size="sm" className="rounded-full">
+                    {hasCopied ? "Copied" : "Copy diagnostics"}
+                </Button>
+                <Button onClick={() => handleAction("quit_app")} intent="alert-subtle" size="sm" className="rounded-full">
+                    Quit
+                </Button>
+            </div>
+        </>
     )
 }
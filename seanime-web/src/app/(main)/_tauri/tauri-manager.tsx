@@ -1,11 +1,22 @@
 "use client"
 
+import { invoke } from "@tauri-apps/api/core"
 import { listen } from "@tauri-apps/api/event"
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow"
 import { Window } from "@tauri-apps/api/window"
 import mousetrap from "mousetrap"
 import React from "react"
 
+type TauriServerLogEvent = {
+    stream: "stdout" | "stderr"
+    time: string | null
+    level: "trace" | "debug" | "info" | "warn" | "error" | "fatal" | "panic" | null
+    module: string | null
+    message: string
+    fields: Record<string, string>
+    line: string
+}
+
 type TauriManagerProps = {
     children?: React.ReactNode
 }
@@ -19,9 +30,22 @@ export function TauriManager(props: TauriManagerProps) {
     } = props
 
     React.useEffect(() => {
-        const u = listen("message", (event) => {
-            const message = event.payload
-            console.log("Received message from Rust:", message)
+        // The desktop app also sets it once the server is ready, this covers reloads
+        invoke<string>("get_server_url").then((url) => {
+            window.__SEANIME_SERVER_BASE_URL__ = url
+        }).catch((error) => {
+            console.error("Failed to get server URL:", error)
+        })
+
+        const u = listen<TauriServerLogEvent>("server-log", (event) => {
+            const { level, line } = event.payload
+            if (level === "error" || level === "fatal" || level === "panic" || (!level && event.payload.stream === "stderr")) {
+                console.error("Server:", line)
+            } else if (level === "warn") {
+                console.warn("Server:", line)
+            } else {
+                console.log("Server:", line)
+            }
         })
 
         mousetrap.bind("f11", () => {
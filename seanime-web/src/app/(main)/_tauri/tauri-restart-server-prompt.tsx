@@ -1,10 +1,12 @@
+import { TauriServerStateEvent } from "@/app/(main)/_tauri/tauri-crash-screen-error"
 import { isUpdateInstalledAtom, isUpdatingAtom } from "@/app/(main)/_tauri/tauri-update-modal"
 import { websocketConnectedAtom, websocketConnectionErrorCountAtom } from "@/app/websocket-provider"
 import { LuffyError } from "@/components/shared/luffy-error"
 import { Button } from "@/components/ui/button"
 import { LoadingOverlay } from "@/components/ui/loading-spinner"
 import { Modal } from "@/components/ui/modal"
-import { emit } from "@tauri-apps/api/event"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow"
 import { useAtom, useAtomValue } from "jotai/react"
 import React from "react"
@@ -19,20 +21,28 @@ export function TauriRestartServerPrompt() {
     const [hasClickedRestarted, setHasClickedRestarted] = React.useState(false)
     const isUpdatedInstalled = useAtomValue(isUpdateInstalledAtom)
     const isUpdating = useAtomValue(isUpdatingAtom)
+    // Set when the desktop app reports that the server stopped answering health checks
+    const [isUnhealthy, setIsUnhealthy] = React.useState(false)
 
     React.useEffect(() => {
         if (getCurrentWebviewWindow().label === "main") {
             setHasRendered(true)
         }
+        const u = listen<TauriServerStateEvent>("server-state", (event) => {
+            setIsUnhealthy(event.payload.state === "unhealthy")
+        })
+        return () => {
+            u.then((f) => f())
+        }
     }, [])
 
     const handleRestart = async () => {
         setHasClickedRestarted(true)
         toast.info("Restarting server...")
-        emit("restart-server").catch((error) => {
-            console.log("Failed to emit restart-server event:", error)
-        }).then(() => {
-            console.log("Successfully emitted restart-server event")
+        invoke("server_restart").then(() => {
+            console.log("Successfully restarted server")
+        }).catch((error) => {
+            console.log("Failed to restart server:", error)
         })
         React.startTransition(() => {
             setTimeout(() => {
@@ -72,7 +82,7 @@ export function TauriRestartServerPrompt() {
             )}
 
             <Modal
-                open={!isConnected && connectionErrorCount >= 10 && !isUpdatedInstalled}
+                open={((!isConnected && connectionErrorCount >= 10) || isUnhealthy) && !isUpdatedInstalled}
                 onOpenChange={() => {}}
                 hideCloseButton
                 contentClass="max-w-2xl"
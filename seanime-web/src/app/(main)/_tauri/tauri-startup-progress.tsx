@@ -0,0 +1,66 @@
+import { cn } from "@/components/ui/core/styling"
+import { invoke } from "@tauri-apps/api/core"
+import { listen } from "@tauri-apps/api/event"
+import React from "react"
+
+export type TauriStartupStage =
+    | "spawningBinary"
+    | "openingDatabase"
+    | "runningMigrations"
+    | "loadingExtensions"
+    | "httpListening"
+    | "webviewConnected"
+
+export type TauriStageTiming = {
+    stage: TauriStartupStage
+    label: string
+    elapsedMs: number
+}
+
+export type TauriStartupProgress = {
+    stages: TauriStageTiming[]
+    totalMs: number | null
+}
+
+function formatElapsed(ms: number) {
+    return `${(ms / 1000).toFixed(1)}s`
+}
+
+export function TauriStartupProgress() {
+
+    const [progress, setProgress] = React.useState<TauriStartupProgress | null>(null)
+
+    React.useEffect(() => {
+        // The window is created once the server is starting, the first stages may already be reached
+        invoke<TauriStartupProgress>("get_startup_progress").then((progress) => {
+            // Don't overwrite a more recent event
+            setProgress(prev => (prev && prev.stages.length >= progress.stages.length) ? prev : progress)
+        })
+
+        const u = listen<TauriStartupProgress>("startup-progress", (event) => {
+            setProgress(event.payload)
+        })
+        return () => {
+            u.then((f) => f())
+        }
+    }, [])
+
+    if (!progress?.stages.length) return null
+
+    return (
+        <div className="flex flex-col gap-1 text-sm w-64">
+            {progress.stages.map((timing, i) => {
+                const isCurrent = i === progress.stages.length - 1 && progress.totalMs === null
+                return (
+                    <div
+                        key={timing.stage}
+                        className={cn("flex justify-between", isCurrent ? "text-[--foreground]" : "text-[--muted]")}
+                    >
+                        <span>{timing.label}{isCurrent && "..."}</span>
+                        <span className="font-mono">{formatElapsed(timing.elapsedMs)}</span>
+                    </div>
+                )
+            })}
+        </div>
+    )
+}
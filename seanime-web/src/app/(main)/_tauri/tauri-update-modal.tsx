@@ -10,7 +10,7 @@ import { Modal } from "@/components/ui/modal"
 import { VerticalMenu } from "@/components/ui/vertical-menu"
 import { logger } from "@/lib/helpers/debug"
 import { WSEvents } from "@/lib/server/ws-events"
-import { emit } from "@tauri-apps/api/event"
+import { invoke } from "@tauri-apps/api/core"
 import { platform } from "@tauri-apps/plugin-os"
 import { relaunch } from "@tauri-apps/plugin-process"
 import { check, Update } from "@tauri-apps/plugin-updater"
@@ -97,7 +97,7 @@ export function TauriUpdateModal(props: UpdateModalProps) {
             await tauriUpdate.download()
             // Kill the currently running server
             toast.info("Shutting down server...")
-            await emit("kill-server")
+            await invoke("server_stop")
             // Wait 1 second before installing the update
             toast.info("Installing update...")
             setTimeout(async () => {
@@ -1,5 +1,6 @@
 "use client"
 
+import { TauriStartupProgress } from "@/app/(main)/_tauri/tauri-startup-progress"
 import { LoadingOverlay } from "@/components/ui/loading-spinner"
 import Image from "next/image"
 import React from "react"
@@ -17,6 +18,7 @@ export default function Page() {
                 className="animate-pulse"
             />
             Launching...
+            {process.env.NEXT_PUBLIC_PLATFORM === "desktop" && <TauriStartupProgress />}
         </LoadingOverlay>
     )
 
@@ -1,4 +1,4 @@
-import { getServerBaseUrl } from "@/api/client/server-url"
+import { getServerWebSocketUrl } from "@/api/client/server-url"
 import { websocketAtom, WebSocketContext } from "@/app/(main)/_atoms/websocket.atoms"
 import { TauriRestartServerPrompt } from "@/app/(main)/_tauri/tauri-restart-server-prompt"
 import { __openDrawersAtom } from "@/components/ui/drawer"
@@ -43,7 +43,7 @@ export function WebsocketProvider({ children }: { children: React.ReactNode }) {
 
     useEffectOnce(() => {
         function connectWebSocket() {
-            const wsUrl = `${document.location.protocol == "https:" ? "wss" : "ws"}://${getServerBaseUrl(true)}/events`
+            const wsUrl = getServerWebSocketUrl("/events")
             const clientId = cookies["Seanime-Client-Id"] || uuidv4()
 
             const newSocket = new WebSocket(`${wsUrl}?id=${clientId}`)